@@ -0,0 +1,180 @@
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+/// A value that can be stored on the stacks.
+///
+/// `i64` wraps around on overflow (unless [`crate::OverflowMode::Checked`] is used), and with the `bigint` feature
+/// `BigInt` can be used for values that never overflow.
+pub trait Cell: Clone + Debug + Display + Eq + Ord + Hash + From<i64> {
+    fn add(&self, rhs: &Self) -> Self;
+    fn sub(&self, rhs: &Self) -> Self;
+    fn mul(&self, rhs: &Self) -> Self;
+    /// Truncating division along with the remainder, `rhs` is never zero
+    fn div_rem(&self, rhs: &Self) -> (Self, Self);
+
+    /// Like [`Self::add`], but `None` if the result overflowed
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+    /// Like [`Self::sub`], but `None` if the result overflowed
+    fn checked_sub(&self, rhs: &Self) -> Option<Self>;
+    /// Like [`Self::mul`], but `None` if the result overflowed
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+    /// Like [`Self::div_rem`], but `None` if the result overflowed
+    fn checked_div_rem(&self, rhs: &Self) -> Option<(Self, Self)>;
+
+    fn not(&self) -> Self;
+    fn and(&self, rhs: &Self) -> Self;
+    fn or(&self, rhs: &Self) -> Self;
+    fn xor(&self, rhs: &Self) -> Self;
+
+    /// Rotates the bits of the value as a 64 bit integer, or `None` if it doesn't fit in one
+    fn rotate_left(&self, amount: u32) -> Option<Self>;
+    /// Rotates the bits of the value as a 64 bit integer, or `None` if it doesn't fit in one
+    fn rotate_right(&self, amount: u32) -> Option<Self>;
+    /// The value modulo 64, as rotating 64 bits does nothing
+    fn rotation_amount(&self) -> u32;
+
+    /// The lowest byte of the value, for writing it as a character
+    fn to_byte(&self) -> u8;
+}
+
+impl Cell for i64 {
+    fn add(&self, rhs: &Self) -> Self {
+        self.wrapping_add(*rhs)
+    }
+
+    fn sub(&self, rhs: &Self) -> Self {
+        self.wrapping_sub(*rhs)
+    }
+
+    fn mul(&self, rhs: &Self) -> Self {
+        self.wrapping_mul(*rhs)
+    }
+
+    fn div_rem(&self, rhs: &Self) -> (Self, Self) {
+        // only i64::MIN / -1 wraps, giving i64::MIN remainder 0, which * undoes
+        (self.wrapping_div(*rhs), self.wrapping_rem(*rhs))
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        i64::checked_add(*self, *rhs)
+    }
+
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        i64::checked_sub(*self, *rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        i64::checked_mul(*self, *rhs)
+    }
+
+    fn checked_div_rem(&self, rhs: &Self) -> Option<(Self, Self)> {
+        Some((self.checked_div(*rhs)?, self.checked_rem(*rhs)?))
+    }
+
+    fn not(&self) -> Self {
+        !self
+    }
+
+    fn and(&self, rhs: &Self) -> Self {
+        self & rhs
+    }
+
+    fn or(&self, rhs: &Self) -> Self {
+        self | rhs
+    }
+
+    fn xor(&self, rhs: &Self) -> Self {
+        self ^ rhs
+    }
+
+    fn rotate_left(&self, amount: u32) -> Option<Self> {
+        Some(i64::rotate_left(*self, amount))
+    }
+
+    fn rotate_right(&self, amount: u32) -> Option<Self> {
+        Some(i64::rotate_right(*self, amount))
+    }
+
+    fn rotation_amount(&self) -> u32 {
+        self.rem_euclid(64) as u32
+    }
+
+    fn to_byte(&self) -> u8 {
+        *self as u8
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl Cell for num_bigint::BigInt {
+    fn add(&self, rhs: &Self) -> Self {
+        self + rhs
+    }
+
+    fn sub(&self, rhs: &Self) -> Self {
+        self - rhs
+    }
+
+    fn mul(&self, rhs: &Self) -> Self {
+        self * rhs
+    }
+
+    fn div_rem(&self, rhs: &Self) -> (Self, Self) {
+        num_integer::Integer::div_rem(self, rhs)
+    }
+
+    // none of these can overflow
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(Cell::add(self, rhs))
+    }
+
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        Some(Cell::sub(self, rhs))
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(Cell::mul(self, rhs))
+    }
+
+    fn checked_div_rem(&self, rhs: &Self) -> Option<(Self, Self)> {
+        Some(Cell::div_rem(self, rhs))
+    }
+
+    fn not(&self) -> Self {
+        !self
+    }
+
+    fn and(&self, rhs: &Self) -> Self {
+        self & rhs
+    }
+
+    fn or(&self, rhs: &Self) -> Self {
+        self | rhs
+    }
+
+    fn xor(&self, rhs: &Self) -> Self {
+        self ^ rhs
+    }
+
+    fn rotate_left(&self, amount: u32) -> Option<Self> {
+        i64::try_from(self)
+            .ok()
+            .map(|x| Self::from(x.rotate_left(amount)))
+    }
+
+    fn rotate_right(&self, amount: u32) -> Option<Self> {
+        i64::try_from(self)
+            .ok()
+            .map(|x| Self::from(x.rotate_right(amount)))
+    }
+
+    fn rotation_amount(&self) -> u32 {
+        let amount = num_integer::Integer::mod_floor(self, &Self::from(64));
+        u32::try_from(amount).expect("a value modulo 64 fits in a u32")
+    }
+
+    fn to_byte(&self) -> u8 {
+        // & on a BigInt works on the two's complement, like it would for an i64
+        u8::try_from(self & Self::from(0xff)).expect("a value masked to 8 bits fits in a u8")
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use crate::{BefreakState, Cell, ExecutionState};
+
+/// Notices when a program gets back to a state it has already been in, so it will loop forever.
+///
+/// Every `interval` steps the whole state (apart from the step count) is hashed and remembered,
+/// so finding a cycle can take up to `interval` times its period.
+///
+/// ```
+/// use array2d::Array2D;
+/// use befreak_core::{BefreakState, CycleDetector, StopReason};
+///
+/// // without an @ there's nothing to stop at
+/// let mut befreak_state: BefreakState = BefreakState::new((0, 0), Array2D::filled_with(' ', 1, 3));
+/// befreak_state.cycle_detector = Some(CycleDetector::new(10));
+/// assert_eq!(
+///     befreak_state.run_for(1000),
+///     StopReason::Cycle { period: 3, first_seen: 10 }
+/// );
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CycleDetector {
+    interval: u64,
+    /// The step each hash was first seen on, sorted so snapshots always come out the same
+    seen: BTreeMap<u64, u64>,
+}
+
+impl Default for CycleDetector {
+    /// Checks every 1000 steps, which barely slows anything down
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl CycleDetector {
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval: interval.max(1),
+            seen: BTreeMap::new(),
+        }
+    }
+
+    /// Forgets every state seen so far
+    pub fn clear(&mut self) {
+        self.seen.clear();
+    }
+
+    /// The period and the step the repeated state was first seen on, if there is a cycle
+    pub(crate) fn check<T: Cell>(&mut self, befreak_state: &BefreakState<T>) -> Option<(u64, u64)> {
+        if befreak_state.step % self.interval != 0 {
+            return None;
+        }
+        let hash = state_hash(befreak_state);
+        match self.seen.get(&hash) {
+            None => {
+                self.seen.insert(hash, befreak_state.step);
+                None
+            }
+            Some(&first_seen) => {
+                let steps = befreak_state.step.abs_diff(first_seen);
+                // the shortest period divides the distance between the two sightings,
+                // and running it again also rules out a hash collision
+                let period = find_period(befreak_state, hash, steps)?;
+                Some((period, first_seen))
+            }
+        }
+    }
+}
+
+fn find_period<T: Cell>(befreak_state: &BefreakState<T>, hash: u64, max_steps: u64) -> Option<u64> {
+    let mut befreak_state = befreak_state.clone();
+    befreak_state.cycle_detector = None;
+    befreak_state.breakpoints.clear();
+    befreak_state.watchpoints.clear();
+    for period in 1..=max_steps {
+        befreak_state.checked_step();
+        if !matches!(befreak_state.state, ExecutionState::Running) {
+            return None;
+        }
+        if state_hash(&befreak_state) == hash {
+            return Some(period);
+        }
+    }
+    None
+}
+
+/// Everything that affects what the program does next, which is everything but the step count
+fn state_hash<T: Cell>(befreak_state: &BefreakState<T>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    befreak_state.stack.hash(&mut hasher);
+    befreak_state.control_stack.hash(&mut hasher);
+    befreak_state.location.hash(&mut hasher);
+    befreak_state.direction.hash(&mut hasher);
+    befreak_state.output_stack.hash(&mut hasher);
+    befreak_state.input_stack.hash(&mut hasher);
+    befreak_state.direction_reversed.hash(&mut hasher);
+    befreak_state.inverse_mode.hash(&mut hasher);
+    befreak_state.string_mode.hash(&mut hasher);
+    hasher.finish()
+}
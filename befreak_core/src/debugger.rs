@@ -0,0 +1,534 @@
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{BefreakState, Cell, Direction};
+
+/// Why a run was paused, see [`crate::ExecutionState::Paused`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PauseReason {
+    /// The next instruction to run has a breakpoint on it, at this (x, y) position
+    Breakpoint((usize, usize)),
+    /// The last instruction set off this watchpoint
+    Watchpoint(Watchpoint),
+    /// The program is back in the state it was in on step `first_seen`, see [`crate::CycleDetector`]
+    Cycle { period: u64, first_seen: u64 },
+}
+
+impl std::fmt::Display for PauseReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Breakpoint(location) => write!(f, "Paused at the breakpoint at {location:?}"),
+            Self::Watchpoint(watchpoint) => write!(f, "Paused by the watchpoint {watchpoint}"),
+            Self::Cycle { period, first_seen } => write!(
+                f,
+                "Stuck in a loop repeating every {period} steps, first seen on step {first_seen}"
+            ),
+        }
+    }
+}
+
+/// Pauses the program before it runs the instruction at `location`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Breakpoint {
+    /// The (x, y) position of the instruction
+    pub location: (usize, usize),
+    /// Only pause if this holds, checked just before the instruction runs
+    pub condition: Option<Condition>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+        })
+    }
+}
+
+impl Comparison {
+    fn compare<V: Ord>(self, lhs: &V, rhs: &V) -> bool {
+        match self {
+            Self::Equal => lhs == rhs,
+            Self::NotEqual => lhs != rhs,
+            Self::Less => lhs < rhs,
+            Self::LessOrEqual => lhs <= rhs,
+            Self::Greater => lhs > rhs,
+            Self::GreaterOrEqual => lhs >= rhs,
+        }
+    }
+}
+
+/// Something about the state of a program, which can be parsed from text like `top == 5`.
+///
+/// The left hand side is one of `top` (the top of the main stack), `control`
+/// (the top of the control stack), `depth` (the length of the main stack),
+/// `control_depth`, `step`, `direction` or `inverse`.
+/// Directions and inverse mode can only be compared with `==`, against
+/// `north`/`south`/`east`/`west` and `true`/`false` respectively.
+/// Conditions on an empty stack never hold.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Condition {
+    StackTop(Comparison, i64),
+    ControlTop(Comparison, i64),
+    StackDepth(Comparison, usize),
+    ControlDepth(Comparison, usize),
+    Step(Comparison, u64),
+    Direction(Direction),
+    InverseMode(bool),
+}
+
+impl Condition {
+    pub fn holds<T: Cell>(&self, befreak_state: &BefreakState<T>) -> bool {
+        match self {
+            Self::StackTop(comparison, value) => befreak_state
+                .stack
+                .last()
+                .is_some_and(|top| comparison.compare(top, &T::from(*value))),
+            Self::ControlTop(comparison, value) => befreak_state
+                .control_stack
+                .last()
+                .is_some_and(|top| comparison.compare(top, &T::from(*value))),
+            Self::StackDepth(comparison, value) => {
+                comparison.compare(&befreak_state.stack.len(), value)
+            }
+            Self::ControlDepth(comparison, value) => {
+                comparison.compare(&befreak_state.control_stack.len(), value)
+            }
+            Self::Step(comparison, value) => comparison.compare(&befreak_state.step, value),
+            Self::Direction(direction) => befreak_state.direction == *direction,
+            Self::InverseMode(inverse_mode) => befreak_state.inverse_mode == *inverse_mode,
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StackTop(comparison, value) => write!(f, "top {comparison} {value}"),
+            Self::ControlTop(comparison, value) => write!(f, "control {comparison} {value}"),
+            Self::StackDepth(comparison, value) => write!(f, "depth {comparison} {value}"),
+            Self::ControlDepth(comparison, value) => {
+                write!(f, "control_depth {comparison} {value}")
+            }
+            Self::Step(comparison, value) => write!(f, "step {comparison} {value}"),
+            Self::Direction(direction) => {
+                let direction = format!("{direction:?}").to_lowercase();
+                write!(f, "direction == {direction}")
+            }
+            Self::InverseMode(inverse_mode) => write!(f, "inverse == {inverse_mode}"),
+        }
+    }
+}
+
+/// Reasons a [`Condition`] or [`Watchpoint`] can't be parsed.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ConditionError {
+    #[error("Expected a condition like \"top == 5\"")]
+    InvalidFormat,
+    #[error("Unknown value {0:?} to compare, expected top, control, depth, control_depth, step, direction or inverse")]
+    UnknownSubject(String),
+    #[error("Unknown comparison {0:?}, expected ==, !=, <, <=, > or >=")]
+    UnknownComparison(String),
+    #[error("{0:?} can only be compared with ==")]
+    OnlyEquality(String),
+    #[error("Invalid value {0:?} to compare against")]
+    InvalidValue(String),
+}
+
+impl FromStr for Condition {
+    type Err = ConditionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [subject, comparison, value] = s
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|_| ConditionError::InvalidFormat)?;
+
+        let comparison = match comparison {
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            _ => return Err(ConditionError::UnknownComparison(comparison.to_string())),
+        };
+        let invalid_value = || ConditionError::InvalidValue(value.to_string());
+
+        match subject {
+            "top" => Ok(Self::StackTop(
+                comparison,
+                value.parse().map_err(|_| invalid_value())?,
+            )),
+            "control" => Ok(Self::ControlTop(
+                comparison,
+                value.parse().map_err(|_| invalid_value())?,
+            )),
+            "depth" => Ok(Self::StackDepth(
+                comparison,
+                value.parse().map_err(|_| invalid_value())?,
+            )),
+            "control_depth" => Ok(Self::ControlDepth(
+                comparison,
+                value.parse().map_err(|_| invalid_value())?,
+            )),
+            "step" => Ok(Self::Step(
+                comparison,
+                value.parse().map_err(|_| invalid_value())?,
+            )),
+            "direction" | "inverse" if comparison != Comparison::Equal => {
+                Err(ConditionError::OnlyEquality(subject.to_string()))
+            }
+            "direction" => Ok(Self::Direction(match value {
+                "north" => Direction::North,
+                "south" => Direction::South,
+                "east" => Direction::East,
+                "west" => Direction::West,
+                _ => return Err(invalid_value()),
+            })),
+            "inverse" => Ok(Self::InverseMode(
+                value.parse().map_err(|_| invalid_value())?,
+            )),
+            _ => Err(ConditionError::UnknownSubject(subject.to_string())),
+        }
+    }
+}
+
+/// Pauses the program after any instruction that changes what is being watched.
+///
+/// Parsed from `top changed`, `control changed`, `output grows`, or a [`Condition`]
+/// which pauses when it starts holding, like `depth == 10` or `top == 0`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Watchpoint {
+    /// The condition didn't hold before the instruction but does after it
+    Condition(Condition),
+    /// The top of the main stack is different, including it being pushed or popped
+    StackTopChanged,
+    /// The top of the control stack is different, including it being pushed or popped
+    ControlTopChanged,
+    /// Something was written, so this never pauses going backwards
+    OutputGrows,
+}
+
+impl std::fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Condition(condition) => write!(f, "{condition}"),
+            Self::StackTopChanged => write!(f, "top changed"),
+            Self::ControlTopChanged => write!(f, "control changed"),
+            Self::OutputGrows => write!(f, "output grows"),
+        }
+    }
+}
+
+impl FromStr for Watchpoint {
+    type Err = ConditionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_whitespace().collect::<Vec<_>>()[..] {
+            ["top", "changed"] => Ok(Self::StackTopChanged),
+            ["control", "changed"] => Ok(Self::ControlTopChanged),
+            ["output", "grows"] => Ok(Self::OutputGrows),
+            _ => Ok(Self::Condition(s.parse()?)),
+        }
+    }
+}
+
+/// What a [`Watchpoint`] looks at, taken before and after each step
+#[derive(PartialEq, Eq)]
+pub(crate) enum Observation<T> {
+    Holds(bool),
+    Top(Option<T>),
+    Length(usize),
+}
+
+impl Watchpoint {
+    pub(crate) fn observe<T: Cell>(&self, befreak_state: &BefreakState<T>) -> Observation<T> {
+        match self {
+            Self::Condition(condition) => Observation::Holds(condition.holds(befreak_state)),
+            Self::StackTopChanged => Observation::Top(befreak_state.stack.last().cloned()),
+            Self::ControlTopChanged => {
+                Observation::Top(befreak_state.control_stack.last().cloned())
+            }
+            Self::OutputGrows => Observation::Length(befreak_state.output_stack.len()),
+        }
+    }
+
+    pub(crate) fn triggered<T: Cell>(
+        &self,
+        before: &Observation<T>,
+        after: &Observation<T>,
+    ) -> bool {
+        match (before, after) {
+            (Observation::Holds(before), Observation::Holds(after)) => !before && *after,
+            (Observation::Length(before), Observation::Length(after)) => after > before,
+            (before, after) => before != after,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExecutionState, StopReason};
+
+    fn load(data: &str) -> BefreakState {
+        BefreakState::new_from_string(data).unwrap()
+    }
+
+    fn paused_at(befreak_state: &BefreakState, location: (usize, usize)) -> bool {
+        matches!(
+            befreak_state.state,
+            ExecutionState::Paused(PauseReason::Breakpoint(at)) if at == location
+        )
+    }
+
+    #[test]
+    fn breakpoint_on_the_first_instruction() {
+        let mut befreak_state = load("@(72w");
+        befreak_state.toggle_breakpoint((1, 0), None);
+        assert_eq!(
+            befreak_state.run_for(100),
+            StopReason::Paused(PauseReason::Breakpoint((1, 0)))
+        );
+        assert_eq!(befreak_state.step, 0);
+        assert!(befreak_state.stack.is_empty());
+
+        assert_eq!(befreak_state.run_for(100), StopReason::Halted);
+        assert_eq!(befreak_state.output(), "H");
+
+        // restarting gets to it again
+        befreak_state.checked_step();
+        assert!(paused_at(&befreak_state, (1, 0)));
+        assert_eq!(befreak_state.step, 0);
+    }
+
+    #[test]
+    fn breakpoint_after_reversing() {
+        let mut befreak_state = load("@(1'w");
+        befreak_state.toggle_breakpoint((2, 0), None);
+        befreak_state.run_for(100);
+        assert!(paused_at(&befreak_state, (2, 0)));
+        befreak_state.run_for(2);
+        assert_eq!(befreak_state.location, (3, 0));
+
+        // undoing the increment leaves the 1 as the next instruction
+        befreak_state.checked_reverse_direction();
+        assert!(paused_at(&befreak_state, (2, 0)));
+        assert_eq!(befreak_state.stack, [1]);
+        assert_eq!(befreak_state.run_for(100), StopReason::ReturnedToStart);
+    }
+
+    #[test]
+    fn breakpoint_after_reversing_at_the_end() {
+        let mut befreak_state = load("@(72w");
+        assert_eq!(befreak_state.run_for(100), StopReason::Halted);
+        befreak_state.toggle_breakpoint((4, 0), None);
+        befreak_state.checked_reverse_direction();
+        assert_eq!(
+            befreak_state.run_for(100),
+            StopReason::Paused(PauseReason::Breakpoint((4, 0)))
+        );
+        assert_eq!(befreak_state.output(), "H");
+        assert_eq!(befreak_state.run_for(100), StopReason::ReturnedToStart);
+    }
+
+    #[test]
+    fn breakpoint_conditions_are_checked_before_the_instruction() {
+        let mut befreak_state = load("@(1'w");
+        befreak_state.toggle_breakpoint((4, 0), Some("top == 2".parse().unwrap()));
+        befreak_state.toggle_breakpoint((3, 0), Some("top == 2".parse().unwrap()));
+        assert_eq!(
+            befreak_state.run_for(100),
+            StopReason::Paused(PauseReason::Breakpoint((4, 0)))
+        );
+        assert_eq!(befreak_state.run_for(100), StopReason::Halted);
+    }
+
+    #[test]
+    fn conditions_parse() {
+        let cases = [
+            ("top == 5", Condition::StackTop(Comparison::Equal, 5)),
+            (
+                "control != 1",
+                Condition::ControlTop(Comparison::NotEqual, 1),
+            ),
+            ("depth < 3", Condition::StackDepth(Comparison::Less, 3)),
+            (
+                "control_depth <= 2",
+                Condition::ControlDepth(Comparison::LessOrEqual, 2),
+            ),
+            ("step > 10", Condition::Step(Comparison::Greater, 10)),
+            (
+                "top >= -4",
+                Condition::StackTop(Comparison::GreaterOrEqual, -4),
+            ),
+            ("direction == north", Condition::Direction(Direction::North)),
+            ("direction == west", Condition::Direction(Direction::West)),
+            ("inverse == true", Condition::InverseMode(true)),
+            ("  top   ==  0 ", Condition::StackTop(Comparison::Equal, 0)),
+        ];
+        for (text, condition) in cases {
+            assert_eq!(text.parse::<Condition>(), Ok(condition.clone()), "{text}");
+            assert_eq!(condition.to_string().parse(), Ok(condition), "{text}");
+        }
+    }
+
+    #[test]
+    fn invalid_conditions_error() {
+        let cases = [
+            ("top", ConditionError::InvalidFormat),
+            ("top == 5 6", ConditionError::InvalidFormat),
+            (
+                "top = 5",
+                ConditionError::UnknownComparison("=".to_string()),
+            ),
+            (
+                "height == 5",
+                ConditionError::UnknownSubject("height".to_string()),
+            ),
+            (
+                "direction < north",
+                ConditionError::OnlyEquality("direction".to_string()),
+            ),
+            (
+                "inverse != true",
+                ConditionError::OnlyEquality("inverse".to_string()),
+            ),
+            (
+                "direction == up",
+                ConditionError::InvalidValue("up".to_string()),
+            ),
+            (
+                "depth == -1",
+                ConditionError::InvalidValue("-1".to_string()),
+            ),
+            (
+                "top == five",
+                ConditionError::InvalidValue("five".to_string()),
+            ),
+        ];
+        for (text, error) in cases {
+            assert_eq!(text.parse::<Condition>(), Err(error), "{text}");
+        }
+    }
+
+    #[test]
+    fn conditions_on_empty_stacks_never_hold() {
+        let befreak_state = load("@");
+        for text in ["top != 5", "control == 0", "top < 0"] {
+            let condition: Condition = text.parse().unwrap();
+            assert!(!condition.holds(&befreak_state), "{text}");
+        }
+        let condition: Condition = "depth == 0".parse().unwrap();
+        assert!(condition.holds(&befreak_state));
+    }
+
+    /// Runs `program` to the end and back with `watchpoint`, giving the steps it paused on
+    fn watchpoint_pauses(program: &str, watchpoint: &str) -> (Vec<u64>, Vec<u64>) {
+        let mut befreak_state = load(program);
+        befreak_state.watchpoints.push(watchpoint.parse().unwrap());
+        let run = |befreak_state: &mut BefreakState, end: StopReason| {
+            let mut pauses = vec![];
+            loop {
+                match befreak_state.run_for(100) {
+                    StopReason::Paused(PauseReason::Watchpoint(..)) => {
+                        pauses.push(befreak_state.step)
+                    }
+                    reason => {
+                        assert_eq!(reason, end, "{program} watching {watchpoint}");
+                        return pauses;
+                    }
+                }
+            }
+        };
+        let forwards = run(&mut befreak_state, StopReason::Halted);
+        befreak_state.checked_reverse_direction();
+        let backwards = run(&mut befreak_state, StopReason::ReturnedToStart);
+        (forwards, backwards)
+    }
+
+    #[test]
+    fn watchpoints_parse() {
+        let cases = [
+            ("top changed", Watchpoint::StackTopChanged),
+            ("control changed", Watchpoint::ControlTopChanged),
+            (" output   grows ", Watchpoint::OutputGrows),
+            (
+                "depth == 10",
+                Watchpoint::Condition(Condition::StackDepth(Comparison::Equal, 10)),
+            ),
+            (
+                "top == 0",
+                Watchpoint::Condition(Condition::StackTop(Comparison::Equal, 0)),
+            ),
+        ];
+        for (text, watchpoint) in cases {
+            assert_eq!(text.parse::<Watchpoint>(), Ok(watchpoint.clone()), "{text}");
+            assert_eq!(watchpoint.to_string().parse(), Ok(watchpoint), "{text}");
+        }
+
+        assert_eq!(
+            "output shrinks".parse::<Watchpoint>(),
+            Err(ConditionError::InvalidFormat)
+        );
+        assert_eq!(
+            "top changes".parse::<Watchpoint>(),
+            Err(ConditionError::InvalidFormat)
+        );
+        assert_eq!(
+            "size == 1".parse::<Watchpoint>(),
+            Err(ConditionError::UnknownSubject("size".to_string()))
+        );
+    }
+
+    #[test]
+    fn top_changed_watchpoints() {
+        // the pushed 0, the 72, then nothing, and the same going back
+        assert_eq!(
+            watchpoint_pauses("@(72w", "top changed"),
+            (vec![1, 2, 3], vec![3, 2, 1])
+        );
+        assert_eq!(
+            watchpoint_pauses("@(5[]5)", "control changed"),
+            (vec![3, 4], vec![4, 3])
+        );
+    }
+
+    #[test]
+    fn condition_watchpoints_pause_when_they_start_holding() {
+        assert_eq!(watchpoint_pauses("@(72w", "top == 72"), (vec![2], vec![3]));
+        // it already holds at the start
+        assert_eq!(watchpoint_pauses("@(72w", "depth == 0"), (vec![3], vec![1]));
+        assert_eq!(watchpoint_pauses("@(72w", "depth > 0"), (vec![1], vec![3]));
+    }
+
+    #[test]
+    fn output_watchpoints_only_pause_going_forwards() {
+        assert_eq!(
+            watchpoint_pauses("@(72w(105w", "output grows"),
+            (vec![3, 6], vec![])
+        );
+    }
+}
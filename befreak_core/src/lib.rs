@@ -0,0 +1,1450 @@
+//! A befreak interpreter, with no dependency on any GUI.
+//!
+//! ```
+//! use befreak_core::{BefreakState, StopReason};
+//!
+//! let mut befreak_state: BefreakState = BefreakState::new_from_string("@(72w")?;
+//! assert_eq!(befreak_state.run_for(1000), StopReason::Halted);
+//! assert_eq!(befreak_state.output(), "H");
+//!
+//! // going backwards undoes everything back to the start
+//! befreak_state.checked_reverse_direction();
+//! assert_eq!(befreak_state.run_for(1000), StopReason::ReturnedToStart);
+//! assert_eq!(befreak_state.output(), "");
+//! # Ok::<(), befreak_core::LoadError>(())
+//! ```
+
+use array2d::Array2D;
+use thiserror::Error;
+
+mod cell;
+pub use cell::Cell;
+mod cycle;
+pub use cycle::CycleDetector;
+mod debugger;
+pub use debugger::{Breakpoint, Comparison, Condition, ConditionError, PauseReason, Watchpoint};
+mod presets;
+pub use presets::{ExpectedResult, Preset, PRESETS};
+mod reversibility;
+#[cfg(feature = "snapshot")]
+mod snapshot;
+#[cfg(feature = "bigint")]
+pub use num_bigint::BigInt;
+pub use reversibility::{assert_reversible, check_reversibility, ReversibilityError};
+#[cfg(feature = "snapshot")]
+pub use snapshot::{SnapshotError, SNAPSHOT_VERSION};
+mod timeline;
+pub use timeline::Timeline;
+#[cfg(feature = "trace")]
+mod trace;
+#[cfg(feature = "trace")]
+pub use trace::{replay, ReplayError, StackDelta, TraceError, TraceRecord, Tracer};
+
+/// Everything that can go wrong while running a program.
+///
+/// Errors never leave the state half modified, so reversing out of one is always possible.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BefreakError {
+    #[error("Tried to enter a position outside the grid")]
+    InvalidPosition,
+    #[error("Tried to run an invalid operator")]
+    InvalidOperation,
+    #[error("Tried to pop off the stack but it was empty")]
+    EmptyMainStack,
+    #[error("Tried to XOR a number onto the top of the stack but it was empty")]
+    LiteralWithoutTarget,
+    #[error("Tried to pop off the control stack but it was empty")]
+    EmptyControlStack,
+    #[error("Tried to pop off the output stack but it was empty")]
+    EmptyOutputStack,
+    #[error("Tried to read a character but the input was empty")]
+    EmptyInputStack,
+    #[error("Tried to use the control stack but a non boolean value was at the top")]
+    NonBoolInControlStack,
+    #[error("Tried to unduplicate the top two values but they were not identical")]
+    InvalidUnduplicate,
+    #[error("Tried to pop a value off the stack but it was not a zero")]
+    InvalidPopZero,
+    #[error("Tried to do under but the top and third values were not identical")]
+    InvalidUnder,
+    #[error("Tried to multiply but the values could not have been left by %")]
+    InvalidMultiply,
+    #[error("Tried to remove a string but it did not match")]
+    InvalidStringRemoval,
+    #[error("Tried to divide by zero")]
+    DivisionByZero,
+    #[error("Tried to rotate a value that does not fit in 64 bits")]
+    RotationOutOfRange,
+    #[error("Tried to run {instruction} on {lhs} and {rhs} but the result overflowed")]
+    Overflow {
+        instruction: char,
+        lhs: String,
+        rhs: String,
+    },
+    #[error("Tried to go back before step 0")]
+    StepUnderflow,
+}
+
+/// A [`BefreakError`] along with the state of the program when it happened.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecutionError {
+    pub error: BefreakError,
+    /// The (x, y) position of the instruction that failed
+    pub location: (usize, usize),
+    /// The character at [`Self::location`], which is only missing if it was outside the grid
+    pub instruction: Option<char>,
+    pub step: u64,
+    pub direction: Direction,
+    pub inverse_mode: bool,
+    pub string_mode: bool,
+}
+
+impl std::fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, ", self.error)?;
+        if let Some(instruction) = self.instruction {
+            write!(f, "running {instruction:?} ")?;
+        }
+        write!(
+            f,
+            "at {:?} on step {} going {:?}",
+            self.location, self.step, self.direction
+        )?;
+        if self.inverse_mode {
+            write!(f, " in inverse mode")?;
+        }
+        if self.string_mode {
+            write!(f, " in string mode")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+/// Reasons a program can't be loaded.
+#[derive(Error, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LoadError {
+    #[error("The program is empty")]
+    EmptyProgram,
+    #[error("The program has no start position (@)")]
+    NoStart,
+    #[error("The program has more than one start position (@), at {0:?}")]
+    MultipleStarts(Vec<(usize, usize)>),
+    #[error("The program has an invalid character {character:?} at {location:?}")]
+    InvalidCharacter {
+        character: char,
+        location: (usize, usize),
+    },
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExecutionState {
+    /// At the start position, either before running or after going all the way backwards
+    NotStarted,
+    Running,
+    /// Stopped part way through, stepping again carries on running
+    Paused(PauseReason),
+    /// Reached the `@` going forwards
+    Done,
+    Error(ExecutionError),
+}
+
+/// Why [`BefreakState::run_for`] or [`BefreakState::run_until_output`] returned
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StopReason {
+    /// Reached the `@` going forwards
+    Halted,
+    /// Reached the `@` going backwards, undoing the whole program
+    ReturnedToStart,
+    Error(ExecutionError),
+    /// A breakpoint or watchpoint paused the program
+    Paused(PauseReason),
+    /// Ran the maximum number of steps without stopping
+    StepBudget,
+    /// The last step wrote something, only for [`BefreakState::run_until_output`]
+    Output,
+    /// The program got back to a state it had been in on step `first_seen`, and will keep
+    /// repeating every `period` steps, see [`BefreakState::cycle_detector`]
+    Cycle {
+        period: u64,
+        first_seen: u64,
+    },
+}
+
+/// What happens when arithmetic gives a result too big for the stack values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverflowMode {
+    /// Wrap around, which can still be undone as every operation wraps the same way
+    #[default]
+    Wrapping,
+    /// Stop with [`BefreakError::Overflow`]
+    Checked,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+/// A befreak program along with everything needed to run it forwards and backwards.
+///
+/// The values on the stacks are `i64`s by default, see [`Cell`] for the alternatives.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BefreakState<T: Cell = i64> {
+    pub stack: Vec<T>,
+    pub control_stack: Vec<T>,
+    /// The (x, y) position of the last executed instruction
+    pub location: (usize, usize),
+    pub direction: Direction,
+    pub output_stack: Vec<T>,
+    // next character to be read is at the end
+    pub input_stack: Vec<T>,
+    /// Whether the program is currently being undone
+    pub direction_reversed: bool,
+    pub inverse_mode: bool,
+    pub string_mode: bool,
+    pub overflow_mode: OverflowMode,
+
+    pub start_pos: (usize, usize),
+    pub state: ExecutionState,
+    /// Number of steps taken from the start, which goes back down when reversing
+    pub step: u64,
+
+    // constants
+    pub code: Array2D<char>,
+    /// The bytes `r` reads, which don't have to be valid UTF-8
+    pub input: Vec<u8>,
+    pub breakpoints: Vec<Breakpoint>,
+    pub watchpoints: Vec<Watchpoint>,
+    /// Pauses the program if it's stuck in a loop, when set
+    pub cycle_detector: Option<CycleDetector>,
+}
+
+impl<T: Cell> BefreakState<T> {
+    /*
+    fn _new_load_file<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(path).unwrap();
+        let mut lines = vec![];
+        for line in io::BufReader::new(file).lines().flatten() {
+            lines.push(line.chars().collect());
+        }
+        let code = Array2D::from_rows(&lines).unwrap();
+
+        match Self::get_start_pos(&code) {
+            None => panic!("No start position"),
+            Some(location) => Self {
+                location,
+                code,
+                ..Self::default()
+            },
+        }
+    }*/
+
+    /// Creates a state that will start at `location`, which should be the `@` in `code`
+    pub fn new(location: (usize, usize), code: Array2D<char>) -> Self {
+        Self {
+            location,
+            code,
+            start_pos: location,
+
+            stack: vec![],
+            control_stack: vec![],
+            direction: Direction::East,
+            output_stack: vec![],
+            input_stack: vec![],
+            input: vec![],
+            breakpoints: vec![],
+            watchpoints: vec![],
+            cycle_detector: None,
+            direction_reversed: false,
+            inverse_mode: false,
+            string_mode: false,
+            overflow_mode: OverflowMode::default(),
+            step: 0,
+            state: ExecutionState::NotStarted,
+        }
+    }
+
+    /// Loads a program from its source, padding short lines with spaces
+    ///
+    /// Blank lines are kept as rows of spaces, apart from a single leading newline
+    /// so programs can be written starting on the line after an opening quote.
+    pub fn new_from_string(data: &str) -> Result<Self, LoadError> {
+        let data = data
+            .strip_prefix('\n')
+            .or_else(|| data.strip_prefix("\r\n"))
+            .unwrap_or(data);
+        let mut lines = vec![];
+        let max_length = data
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        if max_length == 0 {
+            return Err(LoadError::EmptyProgram);
+        }
+        for line in data.lines() {
+            let mut x = line.chars().collect::<Vec<char>>();
+            // things like tabs would make the grid look different to how it runs
+            if let Some(index_x) = x.iter().position(|x| x.is_control()) {
+                return Err(LoadError::InvalidCharacter {
+                    character: x[index_x],
+                    location: (index_x, lines.len()),
+                });
+            }
+            x.resize(max_length, ' ');
+            lines.push(x);
+        }
+        let code = Array2D::from_rows(&lines).expect("all the rows are padded to the same length");
+
+        let location = Self::get_start_pos(&code)?;
+        Ok(Self::new(location, code))
+    }
+
+    /// A 10x10 grid with nothing but a start position
+    pub fn new_empty() -> Self {
+        let mut code = Array2D::filled_with(' ', 10, 10);
+        let _ = code.set(1, 1, '@');
+
+        Self::new((1, 1), code)
+    }
+
+    /// Goes back to the start of the program, keeping the code, input, overflow mode,
+    /// breakpoints and watchpoints
+    pub fn reset(&mut self) {
+        // TODO: remove the clone here, maybe check if this is optimized out or not.
+        let input = std::mem::take(&mut self.input);
+        let breakpoints = std::mem::take(&mut self.breakpoints);
+        let watchpoints = std::mem::take(&mut self.watchpoints);
+        let cycle_detector = self.cycle_detector.take();
+        let overflow_mode = self.overflow_mode;
+        *self = Self::new(self.start_pos, self.code.clone());
+        self.set_input(input);
+        self.breakpoints = breakpoints;
+        self.watchpoints = watchpoints;
+        self.cycle_detector = cycle_detector.map(|mut cycle_detector| {
+            cycle_detector.clear();
+            cycle_detector
+        });
+        self.overflow_mode = overflow_mode;
+    }
+
+    /// Adds a breakpoint at `location`, or removes it if there already was one
+    ///
+    /// ```
+    /// use befreak_core::{BefreakState, PauseReason, StopReason};
+    ///
+    /// let mut befreak_state: BefreakState = BefreakState::new_from_string("@(1'w")?;
+    /// befreak_state.toggle_breakpoint((3, 0), Some("top == 1".parse()?));
+    /// assert_eq!(
+    ///     befreak_state.run_for(1000),
+    ///     StopReason::Paused(PauseReason::Breakpoint((3, 0)))
+    /// );
+    /// assert_eq!(befreak_state.location, (2, 0));
+    /// assert_eq!(befreak_state.run_for(1000), StopReason::Halted);
+    ///
+    /// // going backwards the top is 2 until the increment is undone, so it doesn't pause
+    /// befreak_state.checked_reverse_direction();
+    /// assert_eq!(befreak_state.run_for(1000), StopReason::ReturnedToStart);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn toggle_breakpoint(&mut self, location: (usize, usize), condition: Option<Condition>) {
+        match self
+            .breakpoints
+            .iter()
+            .position(|breakpoint| breakpoint.location == location)
+        {
+            Some(index) => {
+                self.breakpoints.remove(index);
+            }
+            None => self.breakpoints.push(Breakpoint {
+                location,
+                condition,
+            }),
+        }
+    }
+
+    /// Replaces the input read by `r`, discarding anything left of the previous input
+    ///
+    /// `r` reads the input a byte at a time, the same way `w` writes output,
+    /// so anything read and written back out comes out unchanged.
+    pub fn set_input(&mut self, input: impl Into<Vec<u8>>) {
+        let input = input.into();
+        self.input_stack = input.iter().rev().map(|&x| T::from(x as i64)).collect();
+        self.input = input;
+    }
+
+    /// The code as text, in the format [`Self::new_from_string`] reads
+    pub fn serialize(&self) -> String {
+        let mut s: String = String::new();
+        for line in self.code.rows_iter() {
+            s.push_str(&line.collect::<String>());
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Finds the `@` the program starts from, which there must be exactly one of
+    pub fn get_start_pos(code: &Array2D<char>) -> Result<(usize, usize), LoadError> {
+        let mut starts = vec![];
+        for (index_y, row) in code.rows_iter().enumerate() {
+            for (index_x, x) in row.enumerate() {
+                if *x == '@' {
+                    starts.push((index_x, index_y));
+                }
+            }
+        }
+        match starts[..] {
+            [] => Err(LoadError::NoStart),
+            [start] => Ok(start),
+            _ => Err(LoadError::MultipleStarts(starts)),
+        }
+    }
+
+    fn get_instruction(&self, location: (usize, usize)) -> Result<&char, BefreakError> {
+        self.code
+            .get(location.1, location.0)
+            .ok_or(BefreakError::InvalidPosition)
+    }
+
+    /// Switches between running forwards and backwards, storing any error in [`Self::state`]
+    ///
+    /// If the program is running this also undoes the current instruction,
+    /// so the next step moves on to the previous one.
+    pub fn checked_reverse_direction(&mut self) {
+        let run_step = matches!(
+            self.state,
+            ExecutionState::Running | ExecutionState::Paused(..)
+        );
+
+        // states going backwards are different to the ones seen going forwards
+        if let Some(cycle_detector) = &mut self.cycle_detector {
+            cycle_detector.clear();
+        }
+
+        match self.reverse_direction(run_step) {
+            // the next instruction is a different one now, which could have a breakpoint
+            Ok(..) if matches!(self.state, ExecutionState::Running) => {
+                if let Some(reason) = self.breakpoint_reason() {
+                    self.state = ExecutionState::Paused(reason);
+                }
+            }
+            Ok(..) => (),
+            Err(err) => self.state = ExecutionState::Error(self.execution_error(err)),
+        }
+    }
+
+    fn reverse_direction(&mut self, run_step: bool) -> Result<(), BefreakError> {
+        self.direction_reversed = !self.direction_reversed;
+        self.direction = match self.direction {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        };
+        self.inverse_mode = !self.inverse_mode;
+        if matches!(self.state, ExecutionState::Error(..)) {
+            self.state = ExecutionState::Running;
+        };
+        if run_step {
+            self.recover_from_state();
+            self.process_instruction()?;
+        }
+        Ok(())
+    }
+
+    /// The position one cell over from `location`, wrapping around the edges of the grid
+    pub fn move_location(&self, location: (usize, usize), direction: Direction) -> (usize, usize) {
+        let loc;
+        match direction {
+            Direction::North => {
+                if location.1 == 0 {
+                    loc = (location.0, self.code.column_len() - 1);
+                } else {
+                    loc = (location.0, location.1 - 1);
+                }
+            }
+            Direction::South => {
+                if location.1 + 1 >= self.code.column_len() {
+                    loc = (location.0, 0);
+                } else {
+                    loc = (location.0, location.1 + 1);
+                }
+            }
+            Direction::West => {
+                if location.0 == 0 {
+                    loc = (self.code.row_len() - 1, location.1);
+                } else {
+                    loc = (location.0 - 1, location.1);
+                }
+            }
+            Direction::East => {
+                if location.0 + 1 >= self.code.row_len() {
+                    loc = (0, location.1);
+                } else {
+                    loc = (location.0 + 1, location.1);
+                }
+            }
+        };
+        loc
+    }
+
+    // TODO: this is a shit name. rename it
+    fn recover_from_state(&mut self) {
+        match self.state {
+            ExecutionState::Done => {
+                if !self.direction_reversed {
+                    self.reset();
+                }
+                self.state = ExecutionState::Running;
+            }
+
+            ExecutionState::NotStarted => {
+                if self.direction_reversed {
+                    self.reset();
+                }
+                self.state = ExecutionState::Running;
+            }
+
+            ExecutionState::Paused(..) => self.state = ExecutionState::Running,
+
+            ExecutionState::Error(..) | ExecutionState::Running => (),
+        }
+    }
+
+    /// The first watchpoint set off by the last step, otherwise the breakpoint on the next
+    /// instruction, if it should pause
+    fn pause_reason(&self, observations: Vec<debugger::Observation<T>>) -> Option<PauseReason> {
+        let watchpoint = self
+            .watchpoints
+            .iter()
+            .zip(observations)
+            .find(|(watchpoint, before)| watchpoint.triggered(before, &watchpoint.observe(self)));
+        if let Some((watchpoint, _)) = watchpoint {
+            return Some(PauseReason::Watchpoint(watchpoint.clone()));
+        }
+        self.breakpoint_reason()
+    }
+
+    /// The breakpoint on the next instruction, if it should pause
+    fn breakpoint_reason(&self) -> Option<PauseReason> {
+        if self.breakpoints.is_empty() {
+            return None;
+        }
+        let next = self.move_location(self.location, self.direction);
+        self.breakpoints
+            .iter()
+            .find(|breakpoint| {
+                breakpoint.location == next
+                    && breakpoint
+                        .condition
+                        .as_ref()
+                        .map_or(true, |condition| condition.holds(self))
+            })
+            .map(|breakpoint| PauseReason::Breakpoint(breakpoint.location))
+    }
+
+    fn check_cycle(&mut self) -> Option<PauseReason> {
+        let mut cycle_detector = self.cycle_detector.take()?;
+        let cycle = cycle_detector.check(self);
+        self.cycle_detector = Some(cycle_detector);
+        cycle.map(|(period, first_seen)| PauseReason::Cycle { period, first_seen })
+    }
+
+    /// Runs a single instruction, storing any error in [`Self::state`]
+    ///
+    /// Stepping when done or back at the start restarts the program.
+    /// If the next instruction has a breakpoint, this one set off a watchpoint, or a cycle was
+    /// found, the state becomes [`ExecutionState::Paused`] in either direction,
+    /// and the step after that carries on.
+    /// Starting with a breakpoint on the first instruction pauses without running anything.
+    pub fn checked_step(&mut self) {
+        if self.begin_step() {
+            self.finish_step();
+        }
+    }
+
+    /// Gets ready to run a step, returning whether it should go ahead
+    fn begin_step(&mut self) -> bool {
+        // nothing has checked the breakpoint on the first instruction yet
+        let starting = matches!(
+            self.state,
+            ExecutionState::NotStarted | ExecutionState::Done
+        );
+        self.recover_from_state();
+        if !matches!(self.state, ExecutionState::Running) {
+            return false;
+        }
+        if starting {
+            if let Some(reason) = self.breakpoint_reason() {
+                self.state = ExecutionState::Paused(reason);
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Runs the step [`Self::begin_step`] got ready for, then works out whether to pause
+    fn finish_step(&mut self) {
+        let observations = self
+            .watchpoints
+            .iter()
+            .map(|watchpoint| watchpoint.observe(self))
+            .collect();
+        match self.step() {
+            Ok(..) => {
+                // reaching the start or end takes priority over pausing
+                if matches!(self.state, ExecutionState::Running) {
+                    if let Some(reason) = self
+                        .pause_reason(observations)
+                        .or_else(|| self.check_cycle())
+                    {
+                        self.state = ExecutionState::Paused(reason);
+                    }
+                }
+            }
+            Err(err) => self.state = ExecutionState::Error(self.execution_error(err)),
+        }
+    }
+
+    fn execution_error(&self, error: BefreakError) -> ExecutionError {
+        ExecutionError {
+            error,
+            location: self.location,
+            instruction: self.get_instruction(self.location).ok().copied(),
+            step: self.step,
+            direction: self.direction,
+            inverse_mode: self.inverse_mode,
+            string_mode: self.string_mode,
+        }
+    }
+
+    /// Steps until the program stops running (including pausing at a breakpoint)
+    /// or `max_steps` instructions have run
+    pub fn run_for(&mut self, max_steps: u64) -> StopReason {
+        self.run(max_steps, false)
+    }
+
+    /// Like [`Self::run_for`], but also stops after any step that writes output
+    pub fn run_until_output(&mut self, max_steps: u64) -> StopReason {
+        self.run(max_steps, true)
+    }
+
+    /// Steps until the program stops running (including pausing at a breakpoint)
+    /// or `max_steps` instructions have run, returning the state it ended up in
+    #[deprecated(note = "use `run_for`, which also says why it stopped")]
+    pub fn run_until(&mut self, max_steps: u64) -> &ExecutionState {
+        self.run_for(max_steps);
+        &self.state
+    }
+
+    fn run(&mut self, max_steps: u64, stop_on_output: bool) -> StopReason {
+        for _ in 0..max_steps {
+            let output_length = self.output_stack.len();
+            self.checked_step();
+            if let Some(reason) = self.stop_reason() {
+                return reason;
+            }
+            if stop_on_output && self.output_stack.len() > output_length {
+                return StopReason::Output;
+            }
+        }
+        StopReason::StepBudget
+    }
+
+    /// Why running should stop after the last step, if it should
+    fn stop_reason(&self) -> Option<StopReason> {
+        match &self.state {
+            ExecutionState::Running => None,
+            ExecutionState::Done => Some(StopReason::Halted),
+            ExecutionState::NotStarted => Some(StopReason::ReturnedToStart),
+            ExecutionState::Error(err) => Some(StopReason::Error(err.clone())),
+            ExecutionState::Paused(PauseReason::Cycle { period, first_seen }) => {
+                Some(StopReason::Cycle {
+                    period: *period,
+                    first_seen: *first_seen,
+                })
+            }
+            ExecutionState::Paused(reason) => Some(StopReason::Paused(reason.clone())),
+        }
+    }
+
+    /// The output stack as text, with every value treated as a byte of UTF-8
+    pub fn output(&self) -> String {
+        let bytes: Vec<u8> = self.output_stack.iter().map(Cell::to_byte).collect();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    fn step(&mut self) -> Result<(), BefreakError> {
+        // http://tunes.org/~iepos/befreak.html#reference
+
+        // only a snapshot edited by hand can be going backwards from step 0
+        let step = if self.direction_reversed {
+            self.step
+                .checked_sub(1)
+                .ok_or(BefreakError::StepUnderflow)?
+        } else {
+            self.step + 1
+        };
+        self.location = self.move_location(self.location, self.direction);
+        self.step = step;
+
+        self.process_instruction()?;
+        Ok(())
+    }
+
+    fn process_instruction(&mut self) -> Result<(), BefreakError> {
+        if self.string_mode {
+            let char = self.get_instruction(self.location)?;
+            if *char == '"' {
+                self.string_mode = false;
+            } else if self.inverse_mode {
+                let char = T::from(*char as i64);
+                let current = self.pop_main()?;
+                if char != current {
+                    self.stack.push(current);
+                    return Err(BefreakError::InvalidStringRemoval);
+                };
+            } else {
+                self.stack.push(T::from(*char as i64));
+            }
+            return Ok(());
+        }
+
+        if self.get_instruction(self.location)?.is_ascii_digit() {
+            return self.process_number();
+        }
+
+        let mut instruction = *self.get_instruction(self.location)?;
+        if self.inverse_mode {
+            instruction = inverse_instruction(instruction);
+        }
+
+        match instruction {
+            // Push a zero onto the stack
+            '(' => self.stack.push(T::from(0)),
+            // Pop a zero from the stack
+            ')' => {
+                let x = self.pop_main()?;
+                if x != T::from(0) {
+                    self.stack.push(x);
+                    return Err(BefreakError::InvalidPopZero);
+                }
+            }
+
+            // Transfer the top of main stack to control stack
+            '[' => {
+                let x = self.pop_main()?;
+                self.control_stack.push(x);
+            }
+            // Transfer the top of control stack to the main stack
+            ']' => {
+                let x = self.pop_ctrl()?;
+                self.stack.push(x);
+            }
+
+            // Swap the top item with the top of control stack
+            '$' => {
+                let main = self.pop_main()?;
+                match self.control_stack.pop() {
+                    None => {
+                        self.stack.push(main);
+                        return Err(BefreakError::EmptyControlStack);
+                    }
+                    Some(control) => {
+                        self.stack.push(control);
+                        self.control_stack.push(main);
+                    }
+                }
+            }
+
+            // Write the top item to stdout as a character
+            'w' => {
+                if self.inverse_mode {
+                    match self.output_stack.pop() {
+                        None => return Err(BefreakError::EmptyOutputStack),
+                        Some(x) => self.stack.push(x),
+                    };
+                } else {
+                    let x = self.pop_main()?;
+                    self.output_stack.push(x);
+                }
+            }
+
+            // Read a character from the input to the top of stack
+            'r' => {
+                if self.inverse_mode {
+                    let x = self.pop_main()?;
+                    self.input_stack.push(x);
+                } else {
+                    match self.input_stack.pop() {
+                        None => return Err(BefreakError::EmptyInputStack),
+                        Some(x) => self.stack.push(x),
+                    };
+                }
+            }
+
+            // Increment the top item
+            '\'' => {
+                let [x] = self.peek_many()?;
+                let result =
+                    self.arithmetic(instruction, x, &T::from(1), T::add, T::checked_add)?;
+                *self.stack.last_mut().unwrap() = result;
+            }
+            // Decrement the top item
+            '`' => {
+                let [x] = self.peek_many()?;
+                let result =
+                    self.arithmetic(instruction, x, &T::from(1), T::sub, T::checked_sub)?;
+                *self.stack.last_mut().unwrap() = result;
+            }
+
+            // Add the top item to the next item
+            '+' => {
+                let [top, next] = self.peek_many()?;
+                let result = self.arithmetic(instruction, next, top, T::add, T::checked_add)?;
+                let [top, _] = self.pop_many()?;
+                self.stack.push(result);
+                self.stack.push(top);
+            }
+            // Subtract the top item from the next item
+            '-' => {
+                let [top, next] = self.peek_many()?;
+                let result = self.arithmetic(instruction, next, top, T::sub, T::checked_sub)?;
+                let [top, _] = self.pop_many()?;
+                self.stack.push(result);
+                self.stack.push(top);
+            }
+
+            // Divide next by top, leaving a quotient and remainder
+            // [y] [x] -> [y/x] [y%x] [x]
+            '%' => {
+                let [x, y] = self.peek_many()?;
+                if *x == T::from(0) {
+                    return Err(BefreakError::DivisionByZero);
+                }
+                let (quotient, remainder) =
+                    self.arithmetic(instruction, y, x, T::div_rem, T::checked_div_rem)?;
+                let [x, _] = self.pop_many()?;
+                self.stack.push(quotient);
+                self.stack.push(remainder);
+                self.stack.push(x);
+            }
+            // Undo the effects of %, using multiplication
+            '*' => {
+                let [top, remainder, quotient] = self.peek_many()?;
+                let product =
+                    self.arithmetic(instruction, quotient, top, T::mul, T::checked_mul)?;
+                let result =
+                    self.arithmetic(instruction, &product, remainder, T::add, T::checked_add)?;
+                // otherwise % couldn't undo it
+                if *top == T::from(0) {
+                    return Err(BefreakError::InvalidMultiply);
+                }
+                let (undone_quotient, undone_remainder) = result.div_rem(top);
+                if undone_quotient != *quotient || undone_remainder != *remainder {
+                    return Err(BefreakError::InvalidMultiply);
+                }
+                let [top, _, _] = self.pop_many()?;
+                self.stack.push(result);
+                self.stack.push(top);
+            }
+
+            // Bitwise NOT the top item
+            '~' => match self.stack.last_mut() {
+                None => return Err(BefreakError::EmptyMainStack),
+                Some(x) => *x = x.not(),
+            },
+
+            // Bitwise AND top two items, XOR'ing to the third
+            // [z] [y] [x] -> [z^(y&x)] [y] [x]
+            '&' => {
+                let [x, y, z] = self.pop_many()?;
+                self.stack.push(z.xor(&y.and(&x)));
+                self.stack.push(y);
+                self.stack.push(x);
+            }
+            // Bitwise OR top two items, XOR'ing to the third
+            // [z] [y] [x] -> [z^(y|x)] [y] [x]
+            '|' => {
+                let [x, y, z] = self.pop_many()?;
+                self.stack.push(z.xor(&y.or(&x)));
+                self.stack.push(y);
+                self.stack.push(x);
+            }
+            // Bitwise XOR the top item to the next item
+            // [y] [x] -> [y^x] [x]
+            '#' => {
+                let [x, y] = self.pop_many()?;
+                self.stack.push(y.xor(&x));
+                self.stack.push(x);
+            }
+
+            // Rotate means shift with wrapping
+            // Rotate "y" to the left "x" bits
+            //[y] [x] -> [y'] [x]
+            '{' => {
+                let [x, y] = self.pop_many()?;
+                // rotating by 64 bits does nothing, so this also makes
+                // negative amounts rotate the other way
+                match y.rotate_left(x.rotation_amount()) {
+                    None => {
+                        self.stack.push(y);
+                        self.stack.push(x);
+                        return Err(BefreakError::RotationOutOfRange);
+                    }
+                    Some(rotated) => {
+                        self.stack.push(rotated);
+                        self.stack.push(x);
+                    }
+                }
+            }
+            // Rotate "y" to the right "x" bits
+            '}' => {
+                let [x, y] = self.pop_many()?;
+                match y.rotate_right(x.rotation_amount()) {
+                    None => {
+                        self.stack.push(y);
+                        self.stack.push(x);
+                        return Err(BefreakError::RotationOutOfRange);
+                    }
+                    Some(rotated) => {
+                        self.stack.push(rotated);
+                        self.stack.push(x);
+                    }
+                }
+            }
+
+            // Toggle top of control stack (i.e., XOR it with 1)
+            '!' => self.toggle_control_stack()?,
+
+            // If y equals x, toggle top of control stack
+            '=' => {
+                let [top, next] = self.peek_many()?;
+                if next == top {
+                    self.toggle_control_stack()?;
+                }
+            }
+
+            // If y is less than x, toggle top of control stack
+            'l' => {
+                let [top, next] = self.peek_many()?;
+                if next < top {
+                    self.toggle_control_stack()?;
+                }
+            }
+
+            // If y is greater than x, toggle top of control stack
+            'g' => {
+                let [top, next] = self.peek_many()?;
+                if next > top {
+                    self.toggle_control_stack()?;
+                }
+            }
+
+            // Swap the top two items
+            's' => {
+                let [top, next] = self.pop_many()?;
+                self.stack.push(top);
+                self.stack.push(next);
+            }
+
+            // Dig the third item to the top
+            // [z] [y] [x] -> [y] [x] [z]
+            'd' => {
+                let [x, y, z] = self.pop_many()?;
+                self.stack.push(y);
+                self.stack.push(x);
+                self.stack.push(z);
+            }
+            // Bury the first item under the next two
+            // [z] [y] [x] -> [x] [z] [y]
+            'b' => {
+                let [x, y, z] = self.pop_many()?;
+                self.stack.push(x);
+                self.stack.push(z);
+                self.stack.push(y);
+            }
+            // Flip the order of the top three items
+            // [z] [y] [x] -> [x] [y] [z]
+            'f' => {
+                let [x, y, z] = self.pop_many()?;
+                self.stack.push(x);
+                self.stack.push(y);
+                self.stack.push(z);
+            }
+            // Swap the second and third items
+            // [z] [y] [x] -> [y] [z] [x]
+            'c' => {
+                let [x, y, z] = self.pop_many()?;
+                self.stack.push(y);
+                self.stack.push(z);
+                self.stack.push(x);
+            }
+            // "Over": dig copy of second item to the top
+            // [y] [x] -> [y] [x] [y]
+            'o' => {
+                let [x, y] = self.pop_many()?;
+                self.stack.push(y.clone());
+                self.stack.push(x);
+                self.stack.push(y);
+            }
+            // "Under": the inverse of "over"
+            // [y] [x] [y] -> [y] [x]
+            'u' => {
+                let [y1, x, y2] = self.pop_many()?;
+                if y1 != y2 {
+                    self.stack.push(y2);
+                    self.stack.push(x);
+                    self.stack.push(y1);
+                    return Err(BefreakError::InvalidUnder);
+                }
+                self.stack.push(y1);
+                self.stack.push(x);
+            }
+            // Duplicate the top item
+            // [x] -> [x] [x]
+            ':' => {
+                let x = self.pop_main()?;
+                self.stack.push(x.clone());
+                self.stack.push(x);
+            }
+            // Unduplicate the top two items
+            // [x] [x] -> [x]
+            ';' => {
+                let [x1, x2] = self.pop_many()?;
+                if x1 != x2 {
+                    self.stack.push(x2);
+                    self.stack.push(x1);
+                    return Err(BefreakError::InvalidUnduplicate);
+                }
+                self.stack.push(x1);
+            }
+            // Enter string mode
+            '"' => self.string_mode = true,
+            // Toggle inverse mode
+            // the doc says "toggle reverse mode", which doesn't make any sense, as a reverse
+            // mode toggle would just undo the whole program back to the start
+            '?' => self.inverse_mode = !self.inverse_mode,
+            // Halt. Also signals the entrance point for the program
+            '@' => {
+                if self.direction_reversed {
+                    self.state = ExecutionState::NotStarted;
+                    self.reverse_direction(false)?;
+                } else {
+                    self.state = ExecutionState::Done;
+                }
+            }
+            // If going east or west, turn right; otherwise, turn left
+            '\\' => {
+                self.direction = match self.direction {
+                    Direction::North => Direction::West,
+                    Direction::South => Direction::East,
+                    Direction::East => Direction::South,
+                    Direction::West => Direction::North,
+                }
+            }
+            // If going east or west, turn left; otherwise, turn right
+            '/' => {
+                self.direction = match self.direction {
+                    Direction::North => Direction::East,
+                    Direction::South => Direction::West,
+                    Direction::East => Direction::North,
+                    Direction::West => Direction::South,
+                }
+            }
+
+            // If going north, go east and push 1 (in reverse mode, push 0) ...
+            // If going south, go east and push 0 (in reverse mode, push 1) ...
+            // If going west, pop and go south if 0, north if 1. (opposite in reverse mode)
+            // If going east, toggle top of control stack, toggle inverted mode, and go west.
+            '>' => match self.direction {
+                Direction::North => {
+                    self.direction = Direction::East;
+                    self.control_stack.push(bool_cell(!self.inverse_mode));
+                }
+                Direction::South => {
+                    self.direction = Direction::East;
+                    self.control_stack.push(bool_cell(self.inverse_mode));
+                }
+                Direction::West => {
+                    let maybe_dir = self.control_stack.pop();
+                    match maybe_dir {
+                        None => {
+                            return Err(BefreakError::EmptyControlStack);
+                        }
+                        Some(dir) => {
+                            if dir == bool_cell(self.inverse_mode) {
+                                self.direction = Direction::South;
+                            } else if dir == bool_cell(!self.inverse_mode) {
+                                self.direction = Direction::North;
+                            } else {
+                                self.control_stack.push(dir);
+                                return Err(BefreakError::NonBoolInControlStack);
+                            }
+                        }
+                    }
+                }
+                Direction::East => {
+                    self.toggle_control_stack()?;
+                    self.inverse_mode = !self.inverse_mode;
+                    self.direction = Direction::West;
+                }
+            },
+            // If going north, go west and push 0 (in reverse mode, push 1) ...
+            // If going south, go west and push 1 (in reverse mode, push 0) ...
+            // If going east, pop and go north if 0, south if 1. (opposite in reverse mode)
+            // If going west, toggle top of control stack, toggle inverted mode, and go east.
+            '<' => match self.direction {
+                Direction::North => {
+                    self.direction = Direction::West;
+                    self.control_stack.push(bool_cell(self.inverse_mode));
+                }
+                Direction::South => {
+                    self.direction = Direction::West;
+                    self.control_stack.push(bool_cell(!self.inverse_mode));
+                }
+                Direction::East => {
+                    let maybe_dir = self.control_stack.pop();
+                    match maybe_dir {
+                        None => {
+                            return Err(BefreakError::EmptyControlStack);
+                        }
+                        Some(dir) => {
+                            if dir == bool_cell(self.inverse_mode) {
+                                self.direction = Direction::North;
+                            } else if dir == bool_cell(!self.inverse_mode) {
+                                self.direction = Direction::South;
+                            } else {
+                                self.control_stack.push(dir);
+                                return Err(BefreakError::NonBoolInControlStack);
+                            }
+                        }
+                    }
+                }
+                Direction::West => {
+                    self.toggle_control_stack()?;
+                    self.inverse_mode = !self.inverse_mode;
+                    self.direction = Direction::East;
+                }
+            },
+            // If going east, go south and push 1 (in reverse mode, push 0) ...
+            // If going west, go south and push 0 (in reverse mode, push 1) ...
+            // If going north, pop and go west if 0, east if 1. (opposite in reverse mode)
+            // If going south, toggle top of control stack, toggle inverted mode, and go north.
+            'v' => match self.direction {
+                Direction::East => {
+                    self.direction = Direction::South;
+                    self.control_stack.push(bool_cell(!self.inverse_mode));
+                }
+                Direction::West => {
+                    self.direction = Direction::South;
+                    self.control_stack.push(bool_cell(self.inverse_mode));
+                }
+                Direction::North => {
+                    let maybe_dir = self.control_stack.pop();
+                    match maybe_dir {
+                        None => {
+                            return Err(BefreakError::EmptyControlStack);
+                        }
+                        Some(dir) => {
+                            if dir == bool_cell(self.inverse_mode) {
+                                self.direction = Direction::West;
+                            } else if dir == bool_cell(!self.inverse_mode) {
+                                self.direction = Direction::East;
+                            } else {
+                                self.control_stack.push(dir);
+                                return Err(BefreakError::NonBoolInControlStack);
+                            }
+                        }
+                    }
+                }
+                Direction::South => {
+                    self.toggle_control_stack()?;
+                    self.inverse_mode = !self.inverse_mode;
+                    self.direction = Direction::North;
+                }
+            },
+            // If going east, go north and push 0 (in reverse mode, push 1) ...
+            // If going west, go north and push 1 (in reverse mode, push 0) ...
+            // If going south, pop and go east if 0, west if 1. (opposite in reverse mode)
+            // If going north, toggle top of control stack, toggle inverted mode, and go south.
+            '^' => match self.direction {
+                Direction::East => {
+                    self.direction = Direction::North;
+                    self.control_stack.push(bool_cell(self.inverse_mode));
+                }
+                Direction::West => {
+                    self.direction = Direction::North;
+                    self.control_stack.push(bool_cell(!self.inverse_mode));
+                }
+                Direction::South => {
+                    let maybe_dir = self.control_stack.pop();
+                    match maybe_dir {
+                        None => {
+                            return Err(BefreakError::EmptyControlStack);
+                        }
+                        Some(dir) => {
+                            if dir == bool_cell(self.inverse_mode) {
+                                self.direction = Direction::East;
+                            } else if dir == bool_cell(!self.inverse_mode) {
+                                self.direction = Direction::West;
+                            } else {
+                                self.control_stack.push(dir);
+                                return Err(BefreakError::NonBoolInControlStack);
+                            }
+                        }
+                    }
+                }
+                Direction::North => {
+                    self.toggle_control_stack()?;
+                    self.inverse_mode = !self.inverse_mode;
+                    self.direction = Direction::South;
+                }
+            },
+
+            ' ' => (),
+            _ => return Err(BefreakError::InvalidOperation),
+        };
+        Ok(())
+    }
+
+    // The whole number is read at once, leaving the location on its last digit,
+    // so there is never a half read number to deal with when reversing
+    fn process_number(&mut self) -> Result<(), BefreakError> {
+        let mut digits = vec![];
+        let mut location = self.location;
+        let mut end = self.location;
+        while let Some(digit) = self.get_instruction(location)?.to_digit(10) {
+            digits.push(T::from(i64::from(digit)));
+            end = location;
+            location = self.move_location(location, self.direction);
+            // only possible if a whole row or column is digits
+            if location == self.location {
+                break;
+            }
+        }
+
+        // reading backwards, so the digits are in the wrong order
+        if self.inverse_mode {
+            digits.reverse();
+        }
+        let instruction = *self.get_instruction(self.location)?;
+        let ten = T::from(10);
+        let number = digits.iter().try_fold(T::from(0), |number, digit| {
+            let number = self.arithmetic(instruction, &number, &ten, T::mul, T::checked_mul)?;
+            self.arithmetic(instruction, &number, digit, T::add, T::checked_add)
+        })?;
+
+        match self.stack.last_mut() {
+            None => return Err(BefreakError::LiteralWithoutTarget),
+            Some(x) => *x = x.xor(&number),
+        };
+        self.location = end;
+        Ok(())
+    }
+
+    fn pop_main(&mut self) -> Result<T, BefreakError> {
+        self.stack.pop().ok_or(BefreakError::EmptyMainStack)
+    }
+
+    fn pop_many<const LENGTH: usize>(&mut self) -> Result<[T; LENGTH], BefreakError> {
+        // if this errored mid-way through popping it would become impossible to recover from
+        if self.stack.len() < LENGTH {
+            Err(BefreakError::EmptyMainStack)
+        } else {
+            Ok(core::array::from_fn(|_| self.stack.pop().unwrap()))
+        }
+    }
+
+    /// The top values of the stack without popping them, so errors can leave it untouched
+    fn peek_many<const LENGTH: usize>(&self) -> Result<[&T; LENGTH], BefreakError> {
+        if self.stack.len() < LENGTH {
+            Err(BefreakError::EmptyMainStack)
+        } else {
+            Ok(core::array::from_fn(|i| {
+                &self.stack[self.stack.len() - 1 - i]
+            }))
+        }
+    }
+
+    /// Does `lhs <op> rhs`, only erroring on overflow in [`OverflowMode::Checked`]
+    fn arithmetic<R>(
+        &self,
+        instruction: char,
+        lhs: &T,
+        rhs: &T,
+        wrapping: fn(&T, &T) -> R,
+        checked: fn(&T, &T) -> Option<R>,
+    ) -> Result<R, BefreakError> {
+        match self.overflow_mode {
+            OverflowMode::Wrapping => Ok(wrapping(lhs, rhs)),
+            OverflowMode::Checked => checked(lhs, rhs).ok_or_else(|| BefreakError::Overflow {
+                instruction,
+                lhs: lhs.to_string(),
+                rhs: rhs.to_string(),
+            }),
+        }
+    }
+
+    fn pop_ctrl(&mut self) -> Result<T, BefreakError> {
+        self.control_stack
+            .pop()
+            .ok_or(BefreakError::EmptyControlStack)
+    }
+
+    fn toggle_control_stack(&mut self) -> Result<(), BefreakError> {
+        if self.control_stack.is_empty() {
+            return Err(BefreakError::EmptyControlStack);
+        }
+        let top = self.control_stack.last_mut().unwrap();
+        *top = top.xor(&T::from(1));
+        Ok(())
+    }
+}
+
+/// The instruction that `instruction` runs as in inverse mode, which undoes it
+fn inverse_instruction(instruction: char) -> char {
+    match instruction {
+        '(' => ')',
+        ')' => '(',
+
+        '[' => ']',
+        ']' => '[',
+
+        'w' => 'w',
+        'r' => 'r',
+
+        '\'' => '`',
+        '`' => '\'',
+
+        '+' => '-',
+        '-' => '+',
+
+        '%' => '*',
+        '*' => '%',
+
+        '{' => '}',
+        '}' => '{',
+
+        'd' => 'b',
+        'b' => 'd',
+
+        'o' => 'u',
+        'u' => 'o',
+
+        ':' => ';',
+        ';' => ':',
+        _ => instruction,
+    }
+}
+
+fn bool_cell<T: Cell>(value: bool) -> T {
+    T::from(i64::from(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(data: &str) -> Result<BefreakState, LoadError> {
+        BefreakState::new_from_string(data)
+    }
+
+    #[test]
+    fn empty_programs_error() {
+        assert!(matches!(load(""), Err(LoadError::EmptyProgram)));
+        assert!(matches!(load("\n"), Err(LoadError::EmptyProgram)));
+        assert!(matches!(load("\n\n\n"), Err(LoadError::EmptyProgram)));
+    }
+
+    #[test]
+    fn programs_without_a_start_error() {
+        assert!(matches!(load("(72w"), Err(LoadError::NoStart)));
+        assert!(matches!(load("   \n   "), Err(LoadError::NoStart)));
+    }
+
+    #[test]
+    fn programs_with_multiple_starts_error() {
+        match load("@ (\n  @\n@") {
+            Err(LoadError::MultipleStarts(starts)) => {
+                assert_eq!(starts, [(0, 0), (2, 1), (0, 2)]);
+            }
+            result => panic!("expected multiple starts, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn control_characters_error() {
+        match load("@(\t72w") {
+            Err(LoadError::InvalidCharacter {
+                character,
+                location,
+            }) => {
+                assert_eq!(character, '\t');
+                assert_eq!(location, (2, 0));
+            }
+            result => panic!("expected an invalid character, got {result:?}"),
+        }
+        assert!(matches!(
+            load("@(72w\n \x07"),
+            Err(LoadError::InvalidCharacter {
+                character: '\x07',
+                location: (1, 1),
+            })
+        ));
+    }
+
+    #[test]
+    fn blank_lines_are_kept() {
+        let befreak_state = load("@(\n\n72w\n").unwrap();
+        assert_eq!(befreak_state.code.num_rows(), 3);
+        assert_eq!(befreak_state.code.num_columns(), 3);
+        assert!(befreak_state.code.row_iter(1).unwrap().all(|c| *c == ' '));
+        assert_eq!(befreak_state.serialize(), "@( \n   \n72w\n");
+    }
+
+    #[test]
+    fn only_one_leading_newline_is_stripped() {
+        let befreak_state = load("\n@(72w").unwrap();
+        assert_eq!(befreak_state.code.num_rows(), 1);
+        assert_eq!(befreak_state.start_pos, (0, 0));
+
+        let befreak_state = load("\n\n@(72w").unwrap();
+        assert_eq!(befreak_state.code.num_rows(), 2);
+        assert_eq!(befreak_state.start_pos, (0, 1));
+
+        let befreak_state = load("\r\n@(72w").unwrap();
+        assert_eq!(befreak_state.code.num_rows(), 1);
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        for data in [
+            "@(72w",
+            "\n\n@(\n\n 72w\n\n",
+            "  \n@\n",
+            "\"olleh\"@\n   (\n",
+        ] {
+            let serialized = load(data).unwrap().serialize();
+            let reloaded = load(&serialized).unwrap();
+            assert_eq!(reloaded.serialize(), serialized, "{data:?}");
+            assert_eq!(reloaded.code, load(data).unwrap().code, "{data:?}");
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::BefreakError;
+
+/// An example program, with what running it from the start should do
+#[derive(Clone, Debug)]
+pub struct Preset {
+    pub name: &'static str,
+    pub code: &'static str,
+    pub expected: ExpectedResult,
+}
+
+/// How a [`Preset`] should finish
+#[derive(Clone, Debug)]
+pub enum ExpectedResult {
+    /// Halts, having written exactly this
+    Output(&'static str),
+    /// Runs forever, starting by writing this
+    OutputPrefix(&'static str),
+    /// Errors at `location`
+    Error {
+        error: BefreakError,
+        location: (usize, usize),
+    },
+}
+
+/// The programs in the GUI's presets menu, in the order they are shown
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "hello world 1",
+        code: r#"
+/"Hello world!"01\
+\(13v     `wsv)@(/
+    \(=13=13)/    "#,
+        expected: ExpectedResult::Output("Hello world!\n"),
+    },
+    Preset {
+        name: "hello world 2",
+        code: r#"
+/"Hello world!"01\
+\(13vws`v     )@(/
+    (   )         
+    =   3         
+        1         
+    \13=/         "#,
+        expected: ExpectedResult::Output("Hello world!\n"),
+    },
+    Preset {
+        name: "hello world 3",
+        code: r#"
+/"Hello world!"\
+\(13:vwd` v@(10/
+     \=(=)/     "#,
+        expected: ExpectedResult::Output("Hello world!\n"),
+    },
+    Preset {
+        name: "primes 1",
+        code: r#"
+    /1)@(1\         
+    >)1=1(<         
+    \'(v?)/         
+       >'%s(\       
+     ^ >*s)=/       
+     >=<            
+     (              
+/s'0v^?w23(v`s]:(48\
+[   (      )       +
+)   =      =       4
+0   c      c       8
+1   =      =       )
+%   )      (       w
+\01(^      ^)01*01(/"#,
+        expected: ExpectedResult::OutputPrefix("2 3 5 7 11 13 17 19 23 29 31 37 41 43 47 "),
+    },
+    Preset {
+        name: "primes 2",
+        code: r#"
+    /2)@(2\         
+    >)2=2(<         
+    \'(v?)/         
+       s            
+       (            
+       1            
+       >(1=1\       
+       )            
+       1    o       
+       {    *       
+       1    b       
+       (    l       
+       >)u%d/       
+       c            
+       >b'%s(= \    
+     ^ >dc=c*s)/    
+     >=<            
+     d              
+     (              
+/s'0v^?w23(v`s]:(48\
+[   (      )       +
+)   =      =       4
+0   c      c       8
+1   =      =       )
+%   )      (       w
+\01(^      ^)01*01(/"#,
+        expected: ExpectedResult::OutputPrefix("3 5 7 11 13 17 19 23 29 31 37 41 43 47 "),
+    },
+    Preset {
+        name: "error test",
+        code: r#"
+    /2)@(2\         
+    >)2=2(<         
+    \'(v?)/         
+       s            
+       (            
+       1            
+       >(1=1\       
+       )            
+       1    o       
+       {    *       
+       1    b       
+       (    l       
+       >)u%b/       
+       c            
+       >b'%s(= \    
+     ^ >dc=c*s)/    
+     >=<            
+     d              
+     (              
+/s'0v^?w23(v`s]:(48\
+[   (      )       +
+)   =      =       4
+0   c      c       8
+1   =      =       )
+%   )      (       w
+\01(^      ^)01*01(/"#,
+        expected: ExpectedResult::Error {
+            error: BefreakError::InvalidUnder,
+            location: (9, 12),
+        },
+    },
+];
@@ -0,0 +1,159 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use thiserror::Error;
+
+use crate::{BefreakState, Cell, Direction, ExecutionError, ExecutionState};
+
+/// Ways going backwards can fail to undo going forwards.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+pub enum ReversibilityError {
+    #[error("Going backwards did not match going forwards on step {0}")]
+    Diverged(u64),
+    #[error("Going backwards errored: {0}")]
+    Error(ExecutionError),
+    #[error("Going backwards got back to the start, but not to the state it started in")]
+    NotRestored,
+}
+
+/// Runs the program from the start for up to `max_steps` steps (stopping early at the end
+/// or an error), then reverses and checks every step on the way back undoes the one going
+/// forwards, ending in exactly the starting state.
+///
+/// Returns how many steps were run in each direction, or where it first went wrong going
+/// backwards. `befreak_state` isn't changed, as this runs on a copy that has been reset,
+/// without any breakpoints, watchpoints or cycle detection.
+///
+/// ```
+/// use befreak_core::{check_reversibility, BefreakState};
+///
+/// let befreak_state: BefreakState = BefreakState::new_from_string("@(72w")?;
+/// assert_eq!(check_reversibility(&befreak_state, 1000), Ok(4));
+/// # Ok::<(), befreak_core::LoadError>(())
+/// ```
+pub fn check_reversibility<T: Cell>(
+    befreak_state: &BefreakState<T>,
+    max_steps: u64,
+) -> Result<u64, ReversibilityError> {
+    let mut start = befreak_state.clone();
+    start.breakpoints.clear();
+    start.watchpoints.clear();
+    start.cycle_detector = None;
+    start.reset();
+
+    // going backwards, step k should leave the state how it was going forwards before step k,
+    // just facing the other way, with the next instruction being the one before k
+    // (the location itself can differ, as number literals are read from the other end)
+    let mut forwards = vec![Fingerprint::new(&start)];
+    let mut befreak_state = start.clone();
+    for _ in 0..max_steps {
+        befreak_state.checked_step();
+        forwards.push(Fingerprint::new(&befreak_state));
+        if !matches!(befreak_state.state, ExecutionState::Running) {
+            break;
+        }
+    }
+    let steps = befreak_state.step;
+
+    befreak_state.checked_reverse_direction();
+    while !matches!(befreak_state.state, ExecutionState::NotStarted) {
+        if let ExecutionState::Error(err) = befreak_state.state {
+            return Err(ReversibilityError::Error(err));
+        }
+        befreak_state.checked_step();
+        if matches!(befreak_state.state, ExecutionState::NotStarted) {
+            break;
+        }
+
+        let step = befreak_state.step;
+        let next = befreak_state.move_location(befreak_state.location, befreak_state.direction);
+        let matches = usize::try_from(step)
+            .ok()
+            .filter(|&step| step > 0 && step < forwards.len())
+            .is_some_and(|step| {
+                let before = &forwards[step - 1];
+                next == before.location
+                    && data_hash(
+                        &befreak_state,
+                        befreak_state.direction,
+                        befreak_state.inverse_mode,
+                        befreak_state.direction_reversed,
+                    ) == before.reversed_hash
+            });
+        if !matches && !matches!(befreak_state.state, ExecutionState::Error(..)) {
+            return Err(ReversibilityError::Diverged(step));
+        }
+    }
+
+    if same_state(&befreak_state, &start) {
+        Ok(steps)
+    } else {
+        Err(ReversibilityError::NotRestored)
+    }
+}
+
+/// Panics with where it went wrong if [`check_reversibility`] fails, for use in tests
+#[track_caller]
+pub fn assert_reversible<T: Cell>(befreak_state: &BefreakState<T>, max_steps: u64) {
+    if let Err(err) = check_reversibility(befreak_state, max_steps) {
+        panic!("program is not reversible: {err}");
+    }
+}
+
+struct Fingerprint {
+    location: (usize, usize),
+    /// The hash of the state as it would be going backwards
+    reversed_hash: u64,
+}
+
+impl Fingerprint {
+    fn new<T: Cell>(befreak_state: &BefreakState<T>) -> Self {
+        let direction = match befreak_state.direction {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        };
+        Self {
+            location: befreak_state.location,
+            reversed_hash: data_hash(
+                befreak_state,
+                direction,
+                !befreak_state.inverse_mode,
+                !befreak_state.direction_reversed,
+            ),
+        }
+    }
+}
+
+fn data_hash<T: Cell>(
+    befreak_state: &BefreakState<T>,
+    direction: Direction,
+    inverse_mode: bool,
+    direction_reversed: bool,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    befreak_state.stack.hash(&mut hasher);
+    befreak_state.control_stack.hash(&mut hasher);
+    befreak_state.output_stack.hash(&mut hasher);
+    befreak_state.input_stack.hash(&mut hasher);
+    befreak_state.string_mode.hash(&mut hasher);
+    direction.hash(&mut hasher);
+    inverse_mode.hash(&mut hasher);
+    direction_reversed.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn same_state<T: Cell>(lhs: &BefreakState<T>, rhs: &BefreakState<T>) -> bool {
+    lhs.stack == rhs.stack
+        && lhs.control_stack == rhs.control_stack
+        && lhs.output_stack == rhs.output_stack
+        && lhs.input_stack == rhs.input_stack
+        && lhs.location == rhs.location
+        && lhs.direction == rhs.direction
+        && lhs.direction_reversed == rhs.direction_reversed
+        && lhs.inverse_mode == rhs.inverse_mode
+        && lhs.string_mode == rhs.string_mode
+        && lhs.step == rhs.step
+        && matches!(lhs.state, ExecutionState::NotStarted)
+}
@@ -0,0 +1,163 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{BefreakState, Cell, ExecutionState};
+
+/// Written into every snapshot, and bumped whenever [`BefreakState`] changes shape
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// The start of every binary snapshot, so they can be told apart from JSON ones
+const BINARY_MAGIC: &[u8; 4] = b"BFRK";
+
+/// Ways loading or saving a snapshot can fail.
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("The snapshot is version {0}, but only version {SNAPSHOT_VERSION} can be loaded")]
+    UnsupportedVersion(u32),
+    #[error("The snapshot is not valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("The snapshot is not a valid binary snapshot: {0}")]
+    Binary(#[from] bincode::Error),
+    #[error("The snapshot is neither JSON nor a binary snapshot")]
+    UnknownFormat,
+    #[error("The snapshot is not a state a program can be in: {0}")]
+    InvalidState(&'static str),
+}
+
+#[derive(Serialize)]
+struct SnapshotRef<'a, T: Cell> {
+    version: u32,
+    befreak_state: &'a BefreakState<T>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot<T: Cell> {
+    version: u32,
+    befreak_state: BefreakState<T>,
+}
+
+/// Just the start of a snapshot, to check the version before reading the rest
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+impl<T: Cell + Serialize + DeserializeOwned> BefreakState<T> {
+    /// The whole state as JSON, including the stacks, position and modes,
+    /// which [`Self::from_snapshot`] loads back into an identical state
+    ///
+    /// ```
+    /// use befreak_core::{BefreakState, StopReason};
+    ///
+    /// let mut befreak_state: BefreakState = BefreakState::new_from_string("@(72w(105w")?;
+    /// befreak_state.run_for(3);
+    /// let json = befreak_state.to_json_snapshot()?;
+    ///
+    /// let mut resumed: BefreakState = BefreakState::from_snapshot(json.as_bytes())?;
+    /// assert_eq!(resumed.step, 3);
+    /// assert_eq!(resumed.run_for(1000), StopReason::Halted);
+    /// assert_eq!(resumed.output(), "Hi");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn to_json_snapshot(&self) -> Result<String, SnapshotError> {
+        Ok(serde_json::to_string(&SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            befreak_state: self,
+        })?)
+    }
+
+    /// Like [`Self::to_json_snapshot`], but much smaller
+    pub fn to_binary_snapshot(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bincode::serialize_into(
+            &mut bytes,
+            &SnapshotRef {
+                version: SNAPSHOT_VERSION,
+                befreak_state: self,
+            },
+        )?;
+        Ok(bytes)
+    }
+
+    /// Loads a snapshot made by either [`Self::to_json_snapshot`] or [`Self::to_binary_snapshot`]
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if let Some(bytes) = bytes.strip_prefix(BINARY_MAGIC) {
+            return match bincode::deserialize::<Snapshot<T>>(bytes) {
+                Ok(snapshot) => snapshot.into_state(),
+                Err(err) => {
+                    // other versions likely fail to load, so say why
+                    if let Ok(Version { version }) = bincode::deserialize(bytes) {
+                        check_version(version)?;
+                    }
+                    Err(err.into())
+                }
+            };
+        }
+
+        let first = bytes.iter().find(|byte| !byte.is_ascii_whitespace());
+        if first == Some(&b'{') {
+            return match serde_json::from_slice::<Snapshot<T>>(bytes) {
+                Ok(snapshot) => snapshot.into_state(),
+                Err(err) => {
+                    if let Ok(Version { version }) = serde_json::from_slice(bytes) {
+                        check_version(version)?;
+                    }
+                    Err(err.into())
+                }
+            };
+        }
+
+        Err(SnapshotError::UnknownFormat)
+    }
+}
+
+impl<T: Cell> Snapshot<T> {
+    fn into_state(self) -> Result<BefreakState<T>, SnapshotError> {
+        check_version(self.version)?;
+        check_state(&self.befreak_state)?;
+        Ok(self.befreak_state)
+    }
+}
+
+/// Catches snapshots that were edited or corrupted into something that can't be run
+fn check_state<T: Cell>(befreak_state: &BefreakState<T>) -> Result<(), SnapshotError> {
+    let code = &befreak_state.code;
+    if code.num_rows() == 0 || code.num_columns() == 0 {
+        return Err(SnapshotError::InvalidState("the grid is empty"));
+    }
+    let inside = |(x, y): (usize, usize)| x < code.num_columns() && y < code.num_rows();
+    if !inside(befreak_state.location) {
+        return Err(SnapshotError::InvalidState(
+            "the location is outside the grid",
+        ));
+    }
+    if !inside(befreak_state.start_pos) {
+        return Err(SnapshotError::InvalidState("the start is outside the grid"));
+    }
+
+    // once running, `r` in inverse mode can push anything onto the input stack,
+    // but going back to the start always undoes that
+    if matches!(befreak_state.state, ExecutionState::NotStarted) {
+        let input: Vec<T> = befreak_state
+            .input
+            .iter()
+            .rev()
+            .map(|&x| T::from(x as i64))
+            .collect();
+        if befreak_state.input_stack != input {
+            return Err(SnapshotError::InvalidState(
+                "the input stack does not match the input",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_version(version: u32) -> Result<(), SnapshotError> {
+    if version == SNAPSHOT_VERSION {
+        Ok(())
+    } else {
+        Err(SnapshotError::UnsupportedVersion(version))
+    }
+}
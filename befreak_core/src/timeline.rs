@@ -0,0 +1,195 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::{BefreakState, Cell, ExecutionState, StopReason};
+
+/// Copies of the state taken every so often as a program runs forwards,
+/// so [`Self::seek`] can jump to any step without running everything before it again.
+///
+/// Checkpoints are only taken when [`Self::record`] is called, at most one every `interval`
+/// steps. When there are more than `max_checkpoints`, every other one is dropped and the
+/// interval doubles, so long runs don't use up all the memory.
+///
+/// The checkpoints are only valid for one program with one input, so [`Self::clear`] them
+/// when either changes.
+///
+/// ```
+/// use befreak_core::{BefreakState, Timeline, PRESETS};
+///
+/// let primes = PRESETS.iter().find(|preset| preset.name == "primes 1").unwrap();
+/// let mut befreak_state: BefreakState = BefreakState::new_from_string(primes.code)?;
+/// let mut timeline = Timeline::new(100, 1000);
+/// while befreak_state.step < 10_000 {
+///     befreak_state.run_for(10);
+///     timeline.record(&befreak_state);
+/// }
+///
+/// // restores the checkpoint from step 5000, then runs 12 steps
+/// assert_eq!(timeline.seek(&mut befreak_state, 5012), 5012);
+/// let mut expected = befreak_state.clone();
+/// expected.reset();
+/// expected.run_for(5012);
+/// assert_eq!(befreak_state.stack, expected.stack);
+/// # Ok::<(), befreak_core::LoadError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Timeline<T: Cell = i64> {
+    checkpoints: BTreeMap<u64, BefreakState<T>>,
+    interval: u64,
+    max_checkpoints: usize,
+    furthest_step: u64,
+}
+
+impl<T: Cell> Default for Timeline<T> {
+    /// A checkpoint every 1000 steps, keeping up to 256 of them
+    fn default() -> Self {
+        Self::new(1000, 256)
+    }
+}
+
+impl<T: Cell> Timeline<T> {
+    pub fn new(interval: u64, max_checkpoints: usize) -> Self {
+        Self {
+            checkpoints: BTreeMap::new(),
+            interval: interval.max(1),
+            max_checkpoints: max_checkpoints.max(1),
+            furthest_step: 0,
+        }
+    }
+
+    /// Forgets every checkpoint, for when the program or its input changes
+    pub fn clear(&mut self) {
+        *self = Self::new(self.interval, self.max_checkpoints);
+    }
+
+    /// The furthest step any recorded state has got to
+    pub fn furthest_step(&self) -> u64 {
+        self.furthest_step
+    }
+
+    /// The steps that have checkpoints, in order
+    pub fn checkpoints(&self) -> impl Iterator<Item = u64> + '_ {
+        self.checkpoints.keys().copied()
+    }
+
+    /// Takes a checkpoint of `befreak_state` if there isn't already one nearby
+    ///
+    /// Only states running forwards are kept, as going backwards the stacks are
+    /// a step behind [`BefreakState::step`].
+    pub fn record(&mut self, befreak_state: &BefreakState<T>) {
+        let step = befreak_state.step;
+        self.furthest_step = self.furthest_step.max(step);
+        if befreak_state.direction_reversed
+            || !matches!(
+                befreak_state.state,
+                ExecutionState::Running | ExecutionState::Paused(..)
+            )
+        {
+            return;
+        }
+
+        // the start is always available by resetting
+        if step < self.interval {
+            return;
+        }
+        let nearby = step + 1 - self.interval..step + self.interval;
+        if self.checkpoints.range(nearby).next().is_some() {
+            return;
+        }
+        self.checkpoints.insert(step, befreak_state.clone());
+
+        if self.checkpoints.len() > self.max_checkpoints {
+            let mut keep = false;
+            self.checkpoints.retain(|_, _| {
+                keep = !keep;
+                keep
+            });
+            self.interval *= 2;
+        }
+    }
+
+    /// Moves `befreak_state` to `step`, starting from whichever is closest of the start,
+    /// the checkpoints either side of it, or where it already is, then running forwards or
+    /// backwards the rest of the way.
+    ///
+    /// Afterwards the program is going forwards, keeping its breakpoints and watchpoints.
+    /// Returns the step it got to, which is earlier than `step` if the program halted or
+    /// errored before it.
+    pub fn seek(&mut self, befreak_state: &mut BefreakState<T>, step: u64) -> u64 {
+        self.record(befreak_state);
+
+        let distance = |from: u64| from.abs_diff(step);
+        let current = (!befreak_state.direction_reversed
+            && matches!(
+                befreak_state.state,
+                ExecutionState::Running | ExecutionState::Paused(..)
+            ))
+        .then_some(befreak_state.step);
+        let before = self.checkpoints.range(..=step).next_back();
+        let after = self.checkpoints.range(step..).next();
+        let checkpoint = before
+            .into_iter()
+            .chain(after)
+            .min_by_key(|(&checkpoint_step, _)| distance(checkpoint_step))
+            .filter(|(&checkpoint_step, _)| distance(checkpoint_step) < step);
+        let best = checkpoint.map_or(step, |(&checkpoint_step, _)| distance(checkpoint_step));
+
+        if current.map_or(true, |current| distance(current) > best) {
+            match checkpoint {
+                Some((_, checkpoint)) => restore(befreak_state, checkpoint),
+                None => befreak_state.reset(),
+            }
+        }
+
+        match befreak_state.step.cmp(&step) {
+            Ordering::Less => {
+                while befreak_state.step < step {
+                    let steps = (step - befreak_state.step).min(self.interval);
+                    let reason = befreak_state.run_for(steps);
+                    self.record(befreak_state);
+                    if !keeps_going(&reason) {
+                        break;
+                    }
+                }
+            }
+            Ordering::Greater => {
+                // going back over a step undoes it, and turning around redoes the last one
+                // undone, so this ends up exactly how going forwards left it
+                befreak_state.checked_reverse_direction();
+                while befreak_state.step > step {
+                    let reason = befreak_state.run_for(befreak_state.step - step);
+                    if !keeps_going(&reason) {
+                        break;
+                    }
+                }
+                befreak_state.checked_reverse_direction();
+            }
+            Ordering::Equal => (),
+        }
+        befreak_state.step
+    }
+}
+
+/// Whether running can carry on after stopping for `reason`
+fn keeps_going(reason: &StopReason) -> bool {
+    matches!(
+        reason,
+        StopReason::StepBudget | StopReason::Paused(..) | StopReason::Cycle { .. }
+    )
+}
+
+/// Replaces `befreak_state` with `checkpoint`, apart from what isn't part of the program's
+/// progress
+fn restore<T: Cell>(befreak_state: &mut BefreakState<T>, checkpoint: &BefreakState<T>) {
+    let breakpoints = std::mem::take(&mut befreak_state.breakpoints);
+    let watchpoints = std::mem::take(&mut befreak_state.watchpoints);
+    let cycle_detector = befreak_state.cycle_detector.take();
+    *befreak_state = checkpoint.clone();
+    befreak_state.breakpoints = breakpoints;
+    befreak_state.watchpoints = watchpoints;
+    // what it saw before the jump doesn't say anything about what comes after
+    befreak_state.cycle_detector = cycle_detector.map(|mut cycle_detector| {
+        cycle_detector.clear();
+        cycle_detector
+    });
+}
@@ -0,0 +1,281 @@
+use std::io::{self, BufRead, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    inverse_instruction, BefreakError, BefreakState, Cell, Direction, ExecutionState, StopReason,
+};
+
+/// No instruction reaches further down a stack than this
+const MAX_TOUCHED: usize = 3;
+
+/// Ways writing a trace can fail.
+#[derive(Error, Debug)]
+pub enum TraceError {
+    #[error("Could not write the trace: {0}")]
+    Io(#[from] io::Error),
+    #[error("Could not encode a trace record: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Ways a trace can fail to match a program.
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("Could not read the trace: {0}")]
+    Io(#[from] io::Error),
+    #[error("Line {line} of the trace is not a valid record: {error}")]
+    InvalidRecord {
+        line: usize,
+        error: serde_json::Error,
+    },
+    #[error("Line {line} of the trace does not match the program\n  expected: {expected}\n  got:      {actual}")]
+    Mismatch {
+        line: usize,
+        expected: String,
+        actual: String,
+    },
+    #[error(
+        "Line {line} of the trace has a step, but the program had already stopped with an error"
+    )]
+    Stopped { line: usize },
+}
+
+/// How one step changed a stack: the values it took off the top, then the values it put on
+///
+/// Both are in stack order, so the last value is the one that was on top.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackDelta<T> {
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    pub popped: Vec<T>,
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    pub pushed: Vec<T>,
+}
+
+/// Everything one step did, written as one line of a trace by [`Tracer`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceRecord<T> {
+    /// [`BefreakState::step`] after the step, so it goes down when reversing
+    pub step: u64,
+    /// Where the instruction is, which for a number literal is the end it was read from
+    pub location: (usize, usize),
+    pub instruction: char,
+    /// What `instruction` ran as, which differs from it in inverse mode
+    pub effective_instruction: char,
+    /// The direction and modes the instruction ran with
+    pub direction: Direction,
+    pub direction_reversed: bool,
+    pub inverse_mode: bool,
+    pub string_mode: bool,
+    pub stack: StackDelta<T>,
+    pub control_stack: StackDelta<T>,
+    pub output_stack: StackDelta<T>,
+    pub input_stack: StackDelta<T>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<BefreakError>,
+}
+
+/// The top of a stack before a step, which is all of it a step can change
+struct StackTop<T> {
+    length: usize,
+    top: Vec<T>,
+}
+
+impl<T: Cell> StackTop<T> {
+    fn new(stack: &[T]) -> Self {
+        let top = stack.len().saturating_sub(MAX_TOUCHED);
+        Self {
+            length: stack.len(),
+            top: stack[top..].to_vec(),
+        }
+    }
+
+    fn delta(self, stack: &[T]) -> StackDelta<T> {
+        let untouched = (self.length - self.top.len()).min(stack.len());
+        let after = &stack[untouched..];
+        let same = self
+            .top
+            .iter()
+            .zip(after)
+            .take_while(|(before, after)| before == after)
+            .count();
+        StackDelta {
+            popped: self.top[same..].to_vec(),
+            pushed: after[same..].to_vec(),
+        }
+    }
+}
+
+impl<T: Cell> TraceRecord<T> {
+    /// Runs a single step like [`BefreakState::checked_step`], recording what it did
+    ///
+    /// Returns `None` if nothing ran, because the program had stopped with an error or
+    /// paused at a breakpoint on the first instruction.
+    pub fn record_step(befreak_state: &mut BefreakState<T>) -> Option<Self> {
+        // restarting happens before the step, so it isn't part of it
+        if !befreak_state.begin_step() {
+            return None;
+        }
+
+        let location = befreak_state.move_location(befreak_state.location, befreak_state.direction);
+        let instruction = befreak_state.get_instruction(location).ok().copied();
+        let direction = befreak_state.direction;
+        let direction_reversed = befreak_state.direction_reversed;
+        let inverse_mode = befreak_state.inverse_mode;
+        let string_mode = befreak_state.string_mode;
+        let stack = StackTop::new(&befreak_state.stack);
+        let control_stack = StackTop::new(&befreak_state.control_stack);
+        let output_stack = StackTop::new(&befreak_state.output_stack);
+        let input_stack = StackTop::new(&befreak_state.input_stack);
+
+        befreak_state.finish_step();
+
+        let instruction = instruction.unwrap_or(' ');
+        let effective_instruction = if inverse_mode && !string_mode {
+            inverse_instruction(instruction)
+        } else {
+            instruction
+        };
+        let error = match &befreak_state.state {
+            ExecutionState::Error(err) => Some(err.error.clone()),
+            _ => None,
+        };
+        Some(Self {
+            step: befreak_state.step,
+            location,
+            instruction,
+            effective_instruction,
+            direction,
+            direction_reversed,
+            inverse_mode,
+            string_mode,
+            stack: stack.delta(&befreak_state.stack),
+            control_stack: control_stack.delta(&befreak_state.control_stack),
+            output_stack: output_stack.delta(&befreak_state.output_stack),
+            input_stack: input_stack.delta(&befreak_state.input_stack),
+            error,
+        })
+    }
+}
+
+/// Writes a [`TraceRecord`] of every step it runs as JSON Lines, to be checked with [`replay`]
+///
+/// ```
+/// use befreak_core::{replay, BefreakState, StopReason, Tracer};
+///
+/// let start: BefreakState = BefreakState::new_from_string("@(72w")?;
+/// let mut befreak_state = start.clone();
+/// let mut tracer = Tracer::new(vec![]);
+/// assert_eq!(tracer.run_for(&mut befreak_state, 1000)?, StopReason::Halted);
+///
+/// // one line per step
+/// let trace = tracer.into_inner();
+/// assert_eq!(trace.iter().filter(|&&byte| byte == b'\n').count(), 4);
+/// assert_eq!(replay(&start, trace.as_slice())?, 4);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Tracer<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Like [`BefreakState::checked_step`], writing a record of the step if anything ran
+    pub fn step<T: Cell + Serialize>(
+        &mut self,
+        befreak_state: &mut BefreakState<T>,
+    ) -> Result<(), TraceError> {
+        if let Some(record) = TraceRecord::record_step(befreak_state) {
+            serde_json::to_writer(&mut self.writer, &record)?;
+            self.writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Like [`BefreakState::run_for`], writing a record of every step
+    pub fn run_for<T: Cell + Serialize>(
+        &mut self,
+        befreak_state: &mut BefreakState<T>,
+        max_steps: u64,
+    ) -> Result<StopReason, TraceError> {
+        self.run(befreak_state, max_steps, false)
+    }
+
+    /// Like [`BefreakState::run_until_output`], writing a record of every step
+    pub fn run_until_output<T: Cell + Serialize>(
+        &mut self,
+        befreak_state: &mut BefreakState<T>,
+        max_steps: u64,
+    ) -> Result<StopReason, TraceError> {
+        self.run(befreak_state, max_steps, true)
+    }
+
+    fn run<T: Cell + Serialize>(
+        &mut self,
+        befreak_state: &mut BefreakState<T>,
+        max_steps: u64,
+        stop_on_output: bool,
+    ) -> Result<StopReason, TraceError> {
+        for _ in 0..max_steps {
+            let output_length = befreak_state.output_stack.len();
+            self.step(befreak_state)?;
+            if let Some(reason) = befreak_state.stop_reason() {
+                return Ok(reason);
+            }
+            if stop_on_output && befreak_state.output_stack.len() > output_length {
+                return Ok(StopReason::Output);
+            }
+        }
+        Ok(StopReason::StepBudget)
+    }
+
+    /// Stops tracing, giving back the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Runs a copy of `befreak_state` one step per line of a trace written by [`Tracer`],
+/// checking every step does exactly what the trace says it did.
+///
+/// The trace has to have been recorded from the same state, normally a freshly loaded program
+/// with the same input. Returns how many steps were checked, or the first that differed.
+pub fn replay<T: Cell + Serialize + DeserializeOwned>(
+    befreak_state: &BefreakState<T>,
+    trace: impl BufRead,
+) -> Result<usize, ReplayError> {
+    let mut befreak_state = befreak_state.clone();
+    // a breakpoint on the first instruction would pause without a step to check
+    befreak_state.breakpoints.clear();
+    let mut steps = 0;
+    for (index, line) in trace.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let expected: TraceRecord<T> =
+            serde_json::from_str(&line).map_err(|error| ReplayError::InvalidRecord {
+                line: line_number,
+                error,
+            })?;
+        let actual = TraceRecord::record_step(&mut befreak_state)
+            .ok_or(ReplayError::Stopped { line: line_number })?;
+        if actual != expected {
+            // both came from or went through JSON already, so this can't fail
+            let encode =
+                |record: &TraceRecord<T>| serde_json::to_string(record).unwrap_or_default();
+            return Err(ReplayError::Mismatch {
+                line: line_number,
+                expected: encode(&expected),
+                actual: encode(&actual),
+            });
+        }
+        steps += 1;
+    }
+    Ok(steps)
+}
@@ -0,0 +1,165 @@
+//! With `BigInt` values arithmetic never overflows, and still undoes exactly.
+
+#![cfg(feature = "bigint")]
+
+use array2d::Array2D;
+use befreak_core::{BefreakError, BefreakState, BigInt, Cell, ExecutionState, StopReason};
+
+fn big(value: &str) -> BigInt {
+    value.parse().unwrap()
+}
+
+fn bigs(values: &[&str]) -> Vec<BigInt> {
+    values.iter().map(|value| big(value)).collect()
+}
+
+/// A program that is only `instruction`, about to run it going east
+fn program(instruction: char, stack: &[BigInt]) -> BefreakState<BigInt> {
+    let code = Array2D::from_rows(&[vec![' ', instruction, ' ']]).unwrap();
+    let mut befreak_state = BefreakState::new((0, 0), code);
+    befreak_state.stack = stack.to_vec();
+    befreak_state
+}
+
+/// Runs `instruction` once, then runs it again in inverse mode, giving the stack after each
+fn run_and_undo(
+    instruction: char,
+    stack: &[BigInt],
+) -> Result<(Vec<BigInt>, Vec<BigInt>), BefreakError> {
+    let mut befreak_state = program(instruction, stack);
+    befreak_state.checked_step();
+    if let ExecutionState::Error(err) = befreak_state.state {
+        return Err(err.error);
+    }
+    let after = befreak_state.stack.clone();
+
+    befreak_state.location = (0, 0);
+    befreak_state.inverse_mode = true;
+    befreak_state.checked_step();
+    if let ExecutionState::Error(err) = befreak_state.state {
+        panic!("undoing {instruction:?} on {after:?} errored: {err}");
+    }
+    Ok((after, befreak_state.stack))
+}
+
+#[test]
+fn literals_beyond_i64() {
+    let mut befreak_state: BefreakState<BigInt> =
+        BefreakState::new_from_string("@(123456789012345678901234567890").unwrap();
+    befreak_state.run_for(2);
+    assert_eq!(
+        befreak_state.stack,
+        bigs(&["123456789012345678901234567890"])
+    );
+
+    befreak_state.checked_reverse_direction();
+    assert_eq!(befreak_state.run_for(100), StopReason::ReturnedToStart);
+    assert!(befreak_state.stack.is_empty());
+}
+
+#[test]
+fn arithmetic_beyond_i64() {
+    let max = i64::MAX.to_string();
+    let min = i64::MIN.to_string();
+    let cases: &[(char, Vec<BigInt>, Vec<BigInt>)] = &[
+        ('\'', bigs(&[&max]), bigs(&["9223372036854775808"])),
+        ('`', bigs(&[&min]), bigs(&["-9223372036854775809"])),
+        (
+            '+',
+            bigs(&[&max, &max]),
+            bigs(&["18446744073709551614", &max]),
+        ),
+        (
+            '-',
+            bigs(&[&min, &max]),
+            bigs(&["-18446744073709551615", &max]),
+        ),
+        (
+            '*',
+            bigs(&["100000000000000000000", "3", "100000000000000000000"]),
+            bigs(&[
+                "10000000000000000000000000000000000000003",
+                "100000000000000000000",
+            ]),
+        ),
+        (
+            '%',
+            bigs(&[
+                "10000000000000000000000000000000000000003",
+                "100000000000000000000",
+            ]),
+            bigs(&["100000000000000000000", "3", "100000000000000000000"]),
+        ),
+    ];
+    for (instruction, before, after) in cases {
+        let (result, undone) = run_and_undo(*instruction, before)
+            .unwrap_or_else(|err| panic!("{instruction:?} on {before:?} errored: {err}"));
+        assert_eq!(&result, after, "{instruction:?} on {before:?}");
+        assert_eq!(&undone, before, "undoing {instruction:?} on {before:?}");
+    }
+}
+
+/// `%` truncates like it does for `i64`, so `*` rebuilds the same value
+#[test]
+fn division_with_negative_operands() {
+    let cases: &[(&str, &str, &str, &str)] = &[
+        ("-17", "5", "-3", "-2"),
+        ("17", "-5", "-3", "2"),
+        ("-17", "-5", "3", "-2"),
+        (
+            "-100000000000000000007",
+            "10",
+            "-10000000000000000000",
+            "-7",
+        ),
+    ];
+    for &(y, x, quotient, remainder) in cases {
+        let before = bigs(&[y, x]);
+        let (result, undone) = run_and_undo('%', &before).unwrap();
+        assert_eq!(result, bigs(&[quotient, remainder, x]), "{y} % {x}");
+        assert_eq!(undone, before, "undoing {y} % {x}");
+
+        let before = bigs(&[quotient, remainder, x]);
+        let (result, undone) = run_and_undo('*', &before).unwrap();
+        assert_eq!(result, bigs(&[y, x]), "{quotient} * {x} + {remainder}");
+        assert_eq!(undone, before, "undoing {quotient} * {x} + {remainder}");
+    }
+}
+
+/// Negative values are written as the lowest byte of their two's complement, like `i64`s
+#[test]
+fn bytes_of_negative_values() {
+    for value in [-1, -2, -128, -255, -256, -257, i64::MIN] {
+        assert_eq!(BigInt::from(value).to_byte(), value.to_byte(), "{value}");
+    }
+    assert_eq!(big("-18446744073709551617").to_byte(), 0xff);
+
+    let mut befreak_state: BefreakState<BigInt> = BefreakState::new_from_string("@(~w").unwrap();
+    assert_eq!(befreak_state.run_for(100), StopReason::Halted);
+    assert_eq!(befreak_state.output_stack, bigs(&["-1"]));
+    assert_eq!(befreak_state.output_stack[0].to_byte(), 0xff);
+}
+
+/// Rotating is on 64 bits, so only values that fit in an `i64` can be rotated
+#[test]
+fn rotating_values_beyond_i64_errors() {
+    for instruction in ['{', '}'] {
+        for value in ["9223372036854775808", "-9223372036854775809"] {
+            let stack = bigs(&[value, "1"]);
+            let mut befreak_state = program(instruction, &stack);
+            befreak_state.checked_step();
+            match &befreak_state.state {
+                ExecutionState::Error(err) => {
+                    assert_eq!(err.error, BefreakError::RotationOutOfRange)
+                }
+                state => panic!("{instruction:?} on {value} should error, got {state:?}"),
+            }
+            assert_eq!(befreak_state.stack, stack);
+        }
+    }
+
+    // the amount is taken modulo 64, so it can be anything
+    let (result, _) = run_and_undo('{', &bigs(&["1", "100000000000000000003"])).unwrap();
+    let amount = big("100000000000000000003").rotation_amount();
+    assert_eq!(result[0], BigInt::from(1i64.rotate_left(amount)));
+}
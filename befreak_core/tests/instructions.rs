@@ -0,0 +1,564 @@
+//! Every instruction undone by its inverse, and doing what the README reference says it does.
+
+use array2d::Array2D;
+use befreak_core::{
+    assert_reversible, BefreakError, BefreakState, Direction, ExecutionState, OverflowMode,
+    StopReason,
+};
+use proptest::prelude::*;
+
+/// Instructions that only touch the stacks, so can be run on their own
+const INSTRUCTIONS: &[char] = &[
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '(', ')', '[', ']', '$', 'w', 'r', '\'', '`',
+    '+', '-', '%', '*', '~', '&', '|', '#', '{', '}', '!', '=', 'l', 'g', 's', 'd', 'b', 'f', 'c',
+    'o', 'u', ':', ';',
+];
+
+/// Instructions that change direction or mode, which need a two dimensional grid to run in
+const CONTROL_FLOW: &[char] = &['<', '>', 'v', '^', '\\', '/', '?', '"'];
+
+#[derive(Clone, Debug, PartialEq)]
+struct Stacks {
+    stack: Vec<i64>,
+    control_stack: Vec<i64>,
+    output_stack: Vec<i64>,
+    input_stack: Vec<i64>,
+}
+
+impl Stacks {
+    fn main(stack: &[i64]) -> Self {
+        Self {
+            stack: stack.to_vec(),
+            control_stack: vec![],
+            output_stack: vec![],
+            input_stack: vec![],
+        }
+    }
+
+    fn with_control(mut self, control_stack: &[i64]) -> Self {
+        self.control_stack = control_stack.to_vec();
+        self
+    }
+
+    fn of(befreak_state: &BefreakState) -> Self {
+        Self {
+            stack: befreak_state.stack.clone(),
+            control_stack: befreak_state.control_stack.clone(),
+            output_stack: befreak_state.output_stack.clone(),
+            input_stack: befreak_state.input_stack.clone(),
+        }
+    }
+}
+
+/// A program that is only `instruction`, about to run it going east
+fn program(instruction: char, stacks: &Stacks) -> BefreakState {
+    let code = Array2D::from_rows(&[vec![' ', instruction, ' ']]).unwrap();
+    let mut befreak_state = BefreakState::new((0, 0), code);
+    befreak_state.stack = stacks.stack.clone();
+    befreak_state.control_stack = stacks.control_stack.clone();
+    befreak_state.output_stack = stacks.output_stack.clone();
+    befreak_state.input_stack = stacks.input_stack.clone();
+    befreak_state
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::East,
+    Direction::West,
+];
+
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::South,
+        Direction::South => Direction::North,
+        Direction::East => Direction::West,
+        Direction::West => Direction::East,
+    }
+}
+
+/// `instruction` in the middle of a 3x3 grid, about to be entered going `direction`
+fn entering(instruction: char, direction: Direction, control_stack: &[i64]) -> BefreakState {
+    let mut code = Array2D::filled_with(' ', 3, 3);
+    code[(1, 1)] = instruction;
+    let location = match direction {
+        Direction::North => (1, 2),
+        Direction::South => (1, 0),
+        Direction::East => (0, 1),
+        Direction::West => (2, 1),
+    };
+    let mut befreak_state = BefreakState::new(location, code);
+    befreak_state.direction = direction;
+    befreak_state.control_stack = control_stack.to_vec();
+    befreak_state
+}
+
+/// Runs `instruction` once, then runs it again in inverse mode, giving the stacks after each
+fn run_and_undo(instruction: char, stacks: &Stacks) -> Result<(Stacks, Stacks), BefreakError> {
+    let mut befreak_state = program(instruction, stacks);
+    befreak_state.checked_step();
+    if let ExecutionState::Error(err) = befreak_state.state {
+        return Err(err.error);
+    }
+    let after = Stacks::of(&befreak_state);
+
+    befreak_state.location = (0, 0);
+    befreak_state.inverse_mode = true;
+    befreak_state.checked_step();
+    if let ExecutionState::Error(err) = befreak_state.state {
+        panic!("undoing {instruction:?} on {after:?} errored: {err}");
+    }
+    Ok((after, Stacks::of(&befreak_state)))
+}
+
+fn run(instruction: char, stacks: &Stacks) -> Result<Stacks, BefreakError> {
+    let mut befreak_state = program(instruction, stacks);
+    befreak_state.checked_step();
+    match befreak_state.state {
+        ExecutionState::Error(err) => Err(err.error),
+        _ => Ok(Stacks::of(&befreak_state)),
+    }
+}
+
+/// Mostly small values, so comparisons and checks for zero or equal values both pass and fail
+fn value() -> impl Strategy<Value = i64> {
+    prop_oneof![-3i64..=3, any::<i64>()]
+}
+
+fn stacks() -> impl Strategy<Value = Stacks> {
+    (
+        prop::collection::vec(value(), 0..5),
+        prop::collection::vec(value(), 0..3),
+        prop::collection::vec(0i64..128, 0..3),
+        prop::collection::vec(0i64..128, 0..3),
+    )
+        .prop_map(|(stack, control_stack, output_stack, input_stack)| Stacks {
+            stack,
+            control_stack,
+            output_stack,
+            input_stack,
+        })
+}
+
+proptest! {
+    #[test]
+    fn inverse_undoes_instruction(
+        instruction in prop::sample::select(INSTRUCTIONS),
+        stacks in stacks(),
+    ) {
+        match run_and_undo(instruction, &stacks) {
+            Ok((after, undone)) => {
+                prop_assert_eq!(undone, stacks, "{:?} gave {:?}", instruction, after);
+            }
+            // errors have nothing to undo
+            Err(err) => {
+                let mut befreak_state = program(instruction, &stacks);
+                befreak_state.checked_step();
+                prop_assert_eq!(Stacks::of(&befreak_state), stacks, "{:?} errored with {}", instruction, err);
+            }
+        }
+    }
+
+    /// `*` only runs when its input is something `%` could have made, so rarely on random stacks
+    #[test]
+    fn inverse_undoes_multiply(
+        rest in prop::collection::vec(value(), 0..3),
+        y in value(),
+        x in value().prop_filter("dividing by zero", |x| *x != 0),
+    ) {
+        let mut stack = rest;
+        stack.extend([y.wrapping_div(x), y.wrapping_rem(x), x]);
+        let stacks = Stacks::main(&stack);
+        let (_, undone) = run_and_undo('*', &stacks).unwrap();
+        prop_assert_eq!(undone, stacks);
+    }
+
+    /// Whole programs of instructions, running until they halt or error, then back to the start
+    #[test]
+    fn programs_are_reversible(
+        rows in prop::collection::vec(
+            prop::collection::vec(
+                prop::sample::select([INSTRUCTIONS, CONTROL_FLOW].concat()),
+                0..8,
+            ),
+            1..4,
+        ),
+    ) {
+        // `@` is the only start, so it can't be anywhere else
+        let code: Vec<String> = rows.into_iter().map(String::from_iter).collect();
+        let code = format!("@({}", code.join("\n"));
+        let mut befreak_state: BefreakState = BefreakState::new_from_string(&code).unwrap();
+        befreak_state.set_input("hi".to_string());
+        assert_reversible(&befreak_state, 1000);
+    }
+
+    #[test]
+    fn inverse_undoes_control_flow(
+        instruction in prop::sample::select(CONTROL_FLOW),
+        direction in prop::sample::select(&DIRECTIONS[..]),
+        control_stack in prop::collection::vec(value(), 0..3),
+        inverse_mode in any::<bool>(),
+    ) {
+        let mut befreak_state = entering(instruction, direction, &control_stack);
+        befreak_state.inverse_mode = inverse_mode;
+        befreak_state.checked_step();
+        if let ExecutionState::Error(err) = &befreak_state.state {
+            // errors have nothing to undo
+            prop_assert_eq!(&befreak_state.control_stack, &control_stack, "{}", err);
+            return Ok(());
+        }
+
+        befreak_state.checked_reverse_direction();
+        prop_assert!(matches!(befreak_state.state, ExecutionState::Running));
+        prop_assert_eq!(&befreak_state.control_stack, &control_stack);
+        prop_assert_eq!(befreak_state.direction, opposite(direction));
+        prop_assert_eq!(befreak_state.inverse_mode, !inverse_mode);
+        prop_assert!(!befreak_state.string_mode);
+        prop_assert_eq!(befreak_state.location, (1, 1));
+    }
+}
+
+/// The `[z] [y] [x] -> ...` examples from the README's instruction reference, and undoing them
+#[test]
+fn stack_effects() {
+    let cases: &[(char, &[i64], &[i64])] = &[
+        ('5', &[3], &[6]),
+        ('(', &[], &[0]),
+        (')', &[0], &[]),
+        ('\'', &[4], &[5]),
+        ('`', &[4], &[3]),
+        ('+', &[7, 3], &[10, 3]),
+        ('-', &[7, 3], &[4, 3]),
+        ('%', &[17, 5], &[3, 2, 5]),
+        ('%', &[-17, 5], &[-3, -2, 5]),
+        ('*', &[3, 2, 5], &[17, 5]),
+        ('~', &[5], &[-6]),
+        ('&', &[0b1000, 0b1100, 0b1010], &[0b0000, 0b1100, 0b1010]),
+        ('|', &[0b1000, 0b1100, 0b1010], &[0b0110, 0b1100, 0b1010]),
+        ('#', &[0b1100, 0b1010], &[0b0110, 0b1010]),
+        ('{', &[1, 3], &[8, 3]),
+        ('{', &[i64::MIN, 1], &[1, 1]),
+        ('{', &[8, -3], &[1, -3]),
+        ('}', &[8, 3], &[1, 3]),
+        ('}', &[1, 1], &[i64::MIN, 1]),
+        ('}', &[1, 67], &[1 << 61, 67]),
+        ('s', &[1, 2], &[2, 1]),
+        ('d', &[1, 2, 3], &[2, 3, 1]),
+        ('b', &[1, 2, 3], &[3, 1, 2]),
+        ('f', &[1, 2, 3], &[3, 2, 1]),
+        ('c', &[1, 2, 3], &[2, 1, 3]),
+        ('o', &[1, 2], &[1, 2, 1]),
+        ('u', &[1, 2, 1], &[1, 2]),
+        (':', &[4], &[4, 4]),
+        (';', &[4, 4], &[4]),
+    ];
+    for &(instruction, before, after) in cases {
+        let stacks = Stacks::main(before);
+        let (result, undone) = run_and_undo(instruction, &stacks)
+            .unwrap_or_else(|err| panic!("{instruction:?} on {before:?} errored: {err}"));
+        assert_eq!(result, Stacks::main(after), "{instruction:?} on {before:?}");
+        assert_eq!(undone, stacks, "undoing {instruction:?} on {before:?}");
+    }
+}
+
+#[test]
+fn control_stack_effects() {
+    let cases: &[(char, Stacks, Stacks)] = &[
+        (
+            '[',
+            Stacks::main(&[1, 2]).with_control(&[3]),
+            Stacks::main(&[1]).with_control(&[3, 2]),
+        ),
+        (
+            ']',
+            Stacks::main(&[1]).with_control(&[3, 2]),
+            Stacks::main(&[1, 2]).with_control(&[3]),
+        ),
+        (
+            '$',
+            Stacks::main(&[1, 2]).with_control(&[3]),
+            Stacks::main(&[1, 3]).with_control(&[2]),
+        ),
+        (
+            '!',
+            Stacks::main(&[]).with_control(&[0]),
+            Stacks::main(&[]).with_control(&[1]),
+        ),
+        (
+            '=',
+            Stacks::main(&[2, 2]).with_control(&[0]),
+            Stacks::main(&[2, 2]).with_control(&[1]),
+        ),
+        (
+            '=',
+            Stacks::main(&[1, 2]).with_control(&[0]),
+            Stacks::main(&[1, 2]).with_control(&[0]),
+        ),
+        (
+            'l',
+            Stacks::main(&[1, 2]).with_control(&[1]),
+            Stacks::main(&[1, 2]).with_control(&[0]),
+        ),
+        (
+            'l',
+            Stacks::main(&[2, 1]).with_control(&[1]),
+            Stacks::main(&[2, 1]).with_control(&[1]),
+        ),
+        (
+            'g',
+            Stacks::main(&[2, 1]).with_control(&[0]),
+            Stacks::main(&[2, 1]).with_control(&[1]),
+        ),
+        (
+            'g',
+            Stacks::main(&[2, 2]).with_control(&[0]),
+            Stacks::main(&[2, 2]).with_control(&[0]),
+        ),
+    ];
+    for (instruction, before, after) in cases {
+        let (result, undone) = run_and_undo(*instruction, before)
+            .unwrap_or_else(|err| panic!("{instruction:?} on {before:?} errored: {err}"));
+        assert_eq!(&result, after, "{instruction:?} on {before:?}");
+        assert_eq!(&undone, before, "undoing {instruction:?} on {before:?}");
+    }
+}
+
+/// Where each direction changing instruction sends the program, and what it does to the control
+/// stack, going each way
+#[test]
+fn control_flow_effects() {
+    use Direction::*;
+    // the direction going in and the control stack, then the same coming out
+    type Case = (char, Direction, &'static [i64], Direction, &'static [i64]);
+    let cases: &[Case] = &[
+        ('>', North, &[], East, &[1]),
+        ('>', South, &[], East, &[0]),
+        ('>', West, &[0], South, &[]),
+        ('>', West, &[1], North, &[]),
+        ('>', East, &[0], West, &[1]),
+        ('<', North, &[], West, &[0]),
+        ('<', South, &[], West, &[1]),
+        ('<', East, &[0], North, &[]),
+        ('<', East, &[1], South, &[]),
+        ('<', West, &[1], East, &[0]),
+        ('v', East, &[], South, &[1]),
+        ('v', West, &[], South, &[0]),
+        ('v', North, &[0], West, &[]),
+        ('v', North, &[1], East, &[]),
+        ('v', South, &[0], North, &[1]),
+        ('^', East, &[], North, &[0]),
+        ('^', West, &[], North, &[1]),
+        ('^', South, &[0], East, &[]),
+        ('^', South, &[1], West, &[]),
+        ('^', North, &[1], South, &[0]),
+        ('\\', North, &[], West, &[]),
+        ('\\', South, &[], East, &[]),
+        ('\\', East, &[], South, &[]),
+        ('\\', West, &[], North, &[]),
+        ('/', North, &[], East, &[]),
+        ('/', South, &[], West, &[]),
+        ('/', East, &[], North, &[]),
+        ('/', West, &[], South, &[]),
+    ];
+    for &(instruction, direction, before, after, control_after) in cases {
+        let mut befreak_state = entering(instruction, direction, before);
+        befreak_state.checked_step();
+        let case = format!("{instruction:?} going {direction:?} on {before:?}");
+        assert!(
+            matches!(befreak_state.state, ExecutionState::Running),
+            "{case}: {:?}",
+            befreak_state.state
+        );
+        assert_eq!(befreak_state.direction, after, "{case}");
+        assert_eq!(befreak_state.control_stack, control_after, "{case}");
+        // turning back around flips the top of the control stack and inverse mode
+        let turned_back = direction == opposite(after);
+        assert_eq!(befreak_state.inverse_mode, turned_back, "{case}");
+
+        befreak_state.checked_reverse_direction();
+        assert_eq!(
+            befreak_state.direction,
+            opposite(direction),
+            "undoing {case}"
+        );
+        assert_eq!(befreak_state.control_stack, before, "undoing {case}");
+    }
+
+    // inverse mode swaps what is pushed and which way each value goes
+    let mut befreak_state = entering('>', North, &[]);
+    befreak_state.inverse_mode = true;
+    befreak_state.checked_step();
+    assert_eq!(befreak_state.control_stack, [0]);
+    let mut befreak_state = entering('v', North, &[0]);
+    befreak_state.inverse_mode = true;
+    befreak_state.checked_step();
+    assert_eq!(befreak_state.direction, East);
+}
+
+#[test]
+fn mode_toggles() {
+    for direction in DIRECTIONS {
+        let mut befreak_state = entering('?', direction, &[]);
+        befreak_state.checked_step();
+        assert!(befreak_state.inverse_mode);
+        assert_eq!(befreak_state.direction, direction);
+
+        let mut befreak_state = entering('"', direction, &[]);
+        befreak_state.checked_step();
+        assert!(befreak_state.string_mode);
+        assert_eq!(befreak_state.direction, direction);
+    }
+}
+
+/// Branches only take 0 or 1 from the control stack, leaving anything else where it was
+#[test]
+fn branches_need_booleans() {
+    use Direction::*;
+    let cases = [('>', West), ('<', East), ('v', North), ('^', South)];
+    for (instruction, direction) in cases {
+        for (control_stack, error) in [
+            (&[][..], BefreakError::EmptyControlStack),
+            (&[1, 2], BefreakError::NonBoolInControlStack),
+            (&[0, -1], BefreakError::NonBoolInControlStack),
+        ] {
+            let mut befreak_state = entering(instruction, direction, control_stack);
+            befreak_state.checked_step();
+            let case = format!("{instruction:?} going {direction:?} on {control_stack:?}");
+            match &befreak_state.state {
+                ExecutionState::Error(err) => assert_eq!(err.error, error, "{case}"),
+                state => panic!("{case} should error, got {state:?}"),
+            }
+            assert_eq!(befreak_state.control_stack, control_stack, "{case}");
+            assert_eq!(befreak_state.direction, direction, "{case}");
+        }
+    }
+}
+
+#[test]
+fn branching_north_on_a_non_boolean_can_be_reversed() {
+    let mut befreak_state = entering('v', Direction::North, &[5]);
+    befreak_state.checked_step();
+    assert!(matches!(befreak_state.state, ExecutionState::Error(..)));
+    assert_eq!(befreak_state.control_stack, [5]);
+
+    // backing out goes back the way it came, with the 5 still there
+    befreak_state.checked_reverse_direction();
+    befreak_state.checked_step();
+    assert!(matches!(befreak_state.state, ExecutionState::Running));
+    assert_eq!(befreak_state.location, (1, 2));
+    assert_eq!(befreak_state.direction, Direction::South);
+    assert_eq!(befreak_state.control_stack, [5]);
+}
+
+#[test]
+fn input_and_output() {
+    let mut befreak_state = program('w', &Stacks::main(&[1, 72]));
+    befreak_state.checked_step();
+    assert_eq!(befreak_state.stack, [1]);
+    assert_eq!(befreak_state.output(), "H");
+
+    let mut befreak_state = program('r', &Stacks::main(&[1]));
+    befreak_state.set_input("hi".to_string());
+    befreak_state.checked_step();
+    assert_eq!(befreak_state.stack, [1, 'h' as i64]);
+    assert_eq!(befreak_state.input_stack, ['i' as i64]);
+}
+
+/// Input is read as UTF-8 bytes and output written as them, so text passes through unchanged
+#[test]
+fn non_ascii_input_round_trips() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string("@rwrwrwrwrw").unwrap();
+    befreak_state.set_input("é€".to_string());
+    assert_eq!(befreak_state.input_stack.len(), 5);
+    assert_eq!(befreak_state.run_for(100), StopReason::Halted);
+    assert_eq!(befreak_state.output(), "é€");
+    assert!(befreak_state.input_stack.is_empty());
+}
+
+#[test]
+fn invalid_inputs_error() {
+    let cases: &[(char, &[i64], BefreakError)] = &[
+        (')', &[1], BefreakError::InvalidPopZero),
+        ('%', &[1, 0], BefreakError::DivisionByZero),
+        ('*', &[1, 7, 5], BefreakError::InvalidMultiply),
+        ('*', &[1, 2, 0], BefreakError::InvalidMultiply),
+        ('u', &[1, 2, 3], BefreakError::InvalidUnder),
+        (';', &[1, 2], BefreakError::InvalidUnduplicate),
+        ('+', &[1], BefreakError::EmptyMainStack),
+        ('5', &[], BefreakError::LiteralWithoutTarget),
+        ('r', &[], BefreakError::EmptyInputStack),
+        (']', &[], BefreakError::EmptyControlStack),
+    ];
+    for (instruction, before, error) in cases {
+        assert_eq!(
+            run(*instruction, &Stacks::main(before)).as_ref(),
+            Err(error),
+            "{instruction:?} on {before:?}"
+        );
+    }
+}
+
+/// Arithmetic at the edges of `i64` wraps in a way its inverse undoes
+#[test]
+fn arithmetic_edge_cases_are_reversible() {
+    let cases: &[(char, &[i64])] = &[
+        ('%', &[i64::MIN, -1]),
+        ('%', &[i64::MIN, i64::MAX]),
+        ('%', &[-7, 2]),
+        ('*', &[i64::MIN, 0, -1]),
+        ('+', &[i64::MAX, 1]),
+        ('-', &[i64::MIN, 1]),
+        ('\'', &[i64::MAX]),
+        ('`', &[i64::MIN]),
+        ('{', &[1, 64]),
+        ('{', &[1, -1]),
+        ('}', &[i64::MIN, i64::MAX]),
+    ];
+    for (instruction, before) in cases {
+        let stacks = Stacks::main(before);
+        let (_, undone) = run_and_undo(*instruction, &stacks)
+            .unwrap_or_else(|err| panic!("{instruction:?} on {before:?} errored: {err}"));
+        assert_eq!(undone, stacks, "{instruction:?} on {before:?}");
+    }
+}
+
+/// With overflow checked the same edge cases error instead, leaving the stack alone
+#[test]
+fn checked_overflow_errors() {
+    let cases: &[(char, &[i64])] = &[
+        ('+', &[i64::MAX, 1]),
+        ('-', &[i64::MIN, 1]),
+        ('%', &[i64::MIN, -1]),
+        ('\'', &[i64::MAX]),
+        ('`', &[i64::MIN]),
+    ];
+    for (instruction, before) in cases {
+        let mut befreak_state = program(*instruction, &Stacks::main(before));
+        befreak_state.overflow_mode = OverflowMode::Checked;
+        befreak_state.checked_step();
+        match &befreak_state.state {
+            ExecutionState::Error(err) => assert!(
+                matches!(err.error, BefreakError::Overflow { .. }),
+                "{instruction:?} on {before:?} gave {err}"
+            ),
+            state => panic!("{instruction:?} on {before:?} should overflow, got {state:?}"),
+        }
+        assert_eq!(befreak_state.stack, *before);
+    }
+}
+
+#[test]
+fn literal_without_target_can_be_reversed() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string("@12(").unwrap();
+    match befreak_state.run_for(100) {
+        StopReason::Error(err) => {
+            assert_eq!(err.error, BefreakError::LiteralWithoutTarget);
+            assert_eq!(err.location, (1, 0));
+        }
+        reason => panic!("should error, but stopped with {reason:?}"),
+    }
+
+    befreak_state.checked_reverse_direction();
+    assert_eq!(befreak_state.run_for(100), StopReason::ReturnedToStart);
+    assert!(befreak_state.stack.is_empty());
+}
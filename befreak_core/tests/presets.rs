@@ -0,0 +1,59 @@
+//! Every preset does what it says it does, and then can be run backwards to the start.
+
+use befreak_core::{assert_reversible, BefreakState, ExpectedResult, Preset, StopReason, PRESETS};
+
+/// Plenty for any of the presets to halt, or write their expected output
+const MAX_STEPS: u64 = 1_000_000;
+
+fn run_forwards(preset: &Preset, befreak_state: &mut BefreakState) {
+    match &preset.expected {
+        ExpectedResult::Output(output) => {
+            assert_eq!(
+                befreak_state.run_for(MAX_STEPS),
+                StopReason::Halted,
+                "{} should halt",
+                preset.name
+            );
+            assert_eq!(befreak_state.output(), *output, "{}", preset.name);
+        }
+        ExpectedResult::OutputPrefix(output) => {
+            while befreak_state.output().len() < output.len() {
+                assert_eq!(
+                    befreak_state.run_until_output(MAX_STEPS),
+                    StopReason::Output,
+                    "{} should keep writing output",
+                    preset.name
+                );
+            }
+            assert_eq!(befreak_state.output(), *output, "{}", preset.name);
+        }
+        ExpectedResult::Error { error, location } => match befreak_state.run_for(MAX_STEPS) {
+            StopReason::Error(err) => {
+                assert_eq!(err.error, *error, "{}", preset.name);
+                assert_eq!(err.location, *location, "{}", preset.name);
+            }
+            reason => panic!("{} should error, but stopped with {reason:?}", preset.name),
+        },
+    }
+}
+
+#[test]
+fn presets_run_forwards_then_backwards() {
+    for preset in PRESETS {
+        let start: BefreakState = BefreakState::new_from_string(preset.code).unwrap();
+        let mut befreak_state = start.clone();
+        run_forwards(preset, &mut befreak_state);
+        assert_reversible(&start, befreak_state.step);
+    }
+}
+
+#[test]
+fn preset_names_are_unique() {
+    for (i, preset) in PRESETS.iter().enumerate() {
+        assert!(
+            PRESETS[..i].iter().all(|other| other.name != preset.name),
+            "{} is used twice",
+            preset.name
+        );
+    }
+}
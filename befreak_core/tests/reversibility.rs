@@ -0,0 +1,119 @@
+//! Checking reversibility finds the step where going backwards stops undoing going forwards.
+
+use std::fmt::{self, Display};
+
+use befreak_core::{
+    assert_reversible, check_reversibility, BefreakState, Cell, ReversibilityError,
+};
+
+/// An `i64` that saturates instead of wrapping, so incrementing `i64::MAX` can't be undone
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Saturating(i64);
+
+impl From<i64> for Saturating {
+    fn from(value: i64) -> Self {
+        Self(value)
+    }
+}
+
+impl Display for Saturating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Cell for Saturating {
+    fn add(&self, rhs: &Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    fn sub(&self, rhs: &Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    fn mul(&self, rhs: &Self) -> Self {
+        Self(self.0.saturating_mul(rhs.0))
+    }
+
+    fn div_rem(&self, rhs: &Self) -> (Self, Self) {
+        let (div, rem) = self.0.div_rem(&rhs.0);
+        (Self(div), Self(rem))
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        self.0.checked_mul(rhs.0).map(Self)
+    }
+
+    fn checked_div_rem(&self, rhs: &Self) -> Option<(Self, Self)> {
+        let (div, rem) = self.0.checked_div_rem(&rhs.0)?;
+        Some((Self(div), Self(rem)))
+    }
+
+    fn not(&self) -> Self {
+        Self(!self.0)
+    }
+
+    fn and(&self, rhs: &Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+
+    fn or(&self, rhs: &Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+
+    fn xor(&self, rhs: &Self) -> Self {
+        Self(self.0 ^ rhs.0)
+    }
+
+    fn rotate_left(&self, amount: u32) -> Option<Self> {
+        Some(Self(self.0.rotate_left(amount)))
+    }
+
+    fn rotate_right(&self, amount: u32) -> Option<Self> {
+        Some(Self(self.0.rotate_right(amount)))
+    }
+
+    fn rotation_amount(&self) -> u32 {
+        self.0.rotation_amount()
+    }
+
+    fn to_byte(&self) -> u8 {
+        self.0.to_byte()
+    }
+}
+
+/// Pushes `i64::MAX`, increments it, then decrements it and pushes a 0
+const INCREMENT_MAX: &str = "@(9223372036854775807'`(";
+
+#[test]
+fn wrapping_is_reversible() {
+    let befreak_state: BefreakState = BefreakState::new_from_string(INCREMENT_MAX).unwrap();
+    assert_eq!(check_reversibility(&befreak_state, 1000), Ok(6));
+}
+
+#[test]
+fn finds_the_step_that_diverged() {
+    let befreak_state: BefreakState<Saturating> =
+        BefreakState::new_from_string(INCREMENT_MAX).unwrap();
+    // the decrement and the push are undone, then undoing the increment gives i64::MAX - 1
+    assert_eq!(
+        check_reversibility(&befreak_state, 1000),
+        Err(ReversibilityError::Diverged(3))
+    );
+}
+
+#[test]
+#[should_panic(expected = "on step 3")]
+fn assert_reversible_says_where_it_diverged() {
+    let befreak_state: BefreakState<Saturating> =
+        BefreakState::new_from_string(INCREMENT_MAX).unwrap();
+    assert_reversible(&befreak_state, 1000);
+}
@@ -0,0 +1,184 @@
+//! Snapshots load back into exactly the state they were taken from.
+
+#![cfg(feature = "snapshot")]
+
+use array2d::Array2D;
+use befreak_core::{
+    BefreakError, BefreakState, CycleDetector, OverflowMode, SnapshotError, StopReason, Watchpoint,
+    PRESETS, SNAPSHOT_VERSION,
+};
+
+/// A program part way through, with every optional part of the state set
+fn mid_run() -> BefreakState {
+    let preset = PRESETS
+        .iter()
+        .find(|preset| preset.name == "primes 1")
+        .unwrap();
+    let mut befreak_state: BefreakState = BefreakState::new_from_string(preset.code).unwrap();
+    befreak_state.set_input("abc".to_string());
+    befreak_state.overflow_mode = OverflowMode::Checked;
+    befreak_state.toggle_breakpoint((0, 0), Some("top == 5".parse().unwrap()));
+    befreak_state
+        .watchpoints
+        .push("depth == 1000".parse::<Watchpoint>().unwrap());
+    befreak_state.cycle_detector = Some(CycleDetector::default());
+    assert_eq!(befreak_state.run_for(5000), StopReason::StepBudget);
+    befreak_state
+}
+
+fn assert_resumes_identically(mut befreak_state: BefreakState, mut loaded: BefreakState) {
+    assert_eq!(
+        loaded.to_json_snapshot().unwrap(),
+        befreak_state.to_json_snapshot().unwrap()
+    );
+    assert_eq!(loaded.run_for(5000), befreak_state.run_for(5000));
+    assert_eq!(loaded.step, befreak_state.step);
+    assert_eq!(loaded.output(), befreak_state.output());
+    assert_eq!(loaded.stack, befreak_state.stack);
+}
+
+#[test]
+fn json_round_trip() {
+    let befreak_state = mid_run();
+    let json = befreak_state.to_json_snapshot().unwrap();
+    let loaded = BefreakState::from_snapshot(json.as_bytes()).unwrap();
+    assert_resumes_identically(befreak_state, loaded);
+}
+
+#[test]
+fn binary_round_trip() {
+    let befreak_state = mid_run();
+    let bytes = befreak_state.to_binary_snapshot().unwrap();
+    assert!(bytes.len() < befreak_state.to_json_snapshot().unwrap().len());
+    let loaded = BefreakState::from_snapshot(&bytes).unwrap();
+    assert_resumes_identically(befreak_state, loaded);
+}
+
+#[test]
+fn reverses_after_loading() {
+    let json = mid_run().to_json_snapshot().unwrap();
+    let mut loaded: BefreakState = BefreakState::from_snapshot(json.as_bytes()).unwrap();
+    loaded.breakpoints.clear();
+    loaded.checked_reverse_direction();
+    assert_eq!(loaded.run_for(10_000), StopReason::ReturnedToStart);
+    assert_eq!(loaded.output(), "");
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let json = mid_run().to_json_snapshot().unwrap();
+    let json = json.replacen(
+        &format!("\"version\":{SNAPSHOT_VERSION}"),
+        "\"version\":999",
+        1,
+    );
+    assert!(matches!(
+        BefreakState::<i64>::from_snapshot(json.as_bytes()),
+        Err(SnapshotError::UnsupportedVersion(999))
+    ));
+
+    let mut bytes = mid_run().to_binary_snapshot().unwrap();
+    bytes[4..8].copy_from_slice(&999u32.to_le_bytes());
+    assert!(matches!(
+        BefreakState::<i64>::from_snapshot(&bytes),
+        Err(SnapshotError::UnsupportedVersion(999))
+    ));
+}
+
+#[test]
+fn other_files_are_rejected() {
+    assert!(matches!(
+        BefreakState::<i64>::from_snapshot(b"@(72w"),
+        Err(SnapshotError::UnknownFormat)
+    ));
+    let json = format!("{{\"version\": {SNAPSHOT_VERSION}}}");
+    assert!(matches!(
+        BefreakState::<i64>::from_snapshot(json.as_bytes()),
+        Err(SnapshotError::Json(..))
+    ));
+    let mut bytes = b"BFRK".to_vec();
+    bytes.extend(SNAPSHOT_VERSION.to_le_bytes());
+    assert!(matches!(
+        BefreakState::<i64>::from_snapshot(&bytes),
+        Err(SnapshotError::Binary(..))
+    ));
+}
+
+fn assert_invalid(befreak_state: &BefreakState, problem: &str) {
+    let json = befreak_state.to_json_snapshot().unwrap();
+    match BefreakState::<i64>::from_snapshot(json.as_bytes()) {
+        Err(SnapshotError::InvalidState(reason)) => assert!(reason.contains(problem), "{reason}"),
+        result => panic!("expected {problem:?} to be rejected, got {result:?}"),
+    }
+}
+
+#[test]
+fn impossible_states_are_rejected() {
+    let mut empty = mid_run();
+    empty.code = Array2D::filled_with(' ', 0, 0);
+    empty.location = (0, 0);
+    empty.start_pos = (0, 0);
+    assert_invalid(&empty, "grid is empty");
+
+    let mut outside = mid_run();
+    outside.location = (outside.code.num_columns(), 0);
+    assert_invalid(&outside, "location");
+
+    let mut outside = mid_run();
+    outside.start_pos = (0, outside.code.num_rows());
+    assert_invalid(&outside, "start");
+}
+
+#[test]
+fn input_has_to_match_before_starting() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string("@(r)").unwrap();
+    befreak_state.set_input("abc".to_string());
+    let json = befreak_state.to_json_snapshot().unwrap();
+    assert!(BefreakState::<i64>::from_snapshot(json.as_bytes()).is_ok());
+
+    befreak_state.input_stack.pop();
+    assert_invalid(&befreak_state, "input stack");
+    befreak_state.input = b"ab".to_vec();
+    assert_invalid(&befreak_state, "input stack");
+
+    // reading from the input changes the stack but not the input
+    befreak_state.set_input("abc".to_string());
+    befreak_state.run_for(2);
+    assert_eq!(befreak_state.input_stack.len(), 2);
+    let json = befreak_state.to_json_snapshot().unwrap();
+    assert!(BefreakState::<i64>::from_snapshot(json.as_bytes()).is_ok());
+}
+
+#[test]
+fn going_back_before_step_zero_errors() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string("@(72w").unwrap();
+    befreak_state.run_for(1);
+    befreak_state.checked_reverse_direction();
+    let json = befreak_state.to_json_snapshot().unwrap();
+    assert!(json.contains("\"step\":1"));
+    let json = json.replacen("\"step\":1", "\"step\":0", 1);
+
+    let mut loaded: BefreakState = BefreakState::from_snapshot(json.as_bytes()).unwrap();
+    match loaded.run_for(100) {
+        StopReason::Error(err) => {
+            assert_eq!(err.error, BefreakError::StepUnderflow);
+            assert_eq!(err.step, 0);
+        }
+        reason => panic!("should error, but stopped with {reason:?}"),
+    }
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn bigint_round_trip() {
+    use befreak_core::BigInt;
+
+    let mut befreak_state: BefreakState<BigInt> =
+        BefreakState::new_from_string("@(99999999999999999999w").unwrap();
+    befreak_state.run_for(2);
+    assert!(befreak_state.stack[0] > BigInt::from(i64::MAX));
+    let bytes = befreak_state.to_binary_snapshot().unwrap();
+    let loaded: BefreakState<BigInt> = BefreakState::from_snapshot(&bytes).unwrap();
+    assert_eq!(loaded.stack, befreak_state.stack);
+    assert_eq!(loaded.location, befreak_state.location);
+}
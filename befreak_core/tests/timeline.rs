@@ -0,0 +1,108 @@
+//! Seeking to a step gives exactly the state running from the start to it would.
+
+use befreak_core::{BefreakState, ExecutionState, Timeline, PRESETS};
+
+fn primes() -> BefreakState {
+    let preset = PRESETS
+        .iter()
+        .find(|preset| preset.name == "primes 1")
+        .unwrap();
+    BefreakState::new_from_string(preset.code).unwrap()
+}
+
+/// Runs `befreak_state` to `steps`, recording it every few steps like the GUI does
+fn run_recording(befreak_state: &mut BefreakState, timeline: &mut Timeline, steps: u64) {
+    while befreak_state.step < steps {
+        befreak_state.run_for(7.min(steps - befreak_state.step));
+        timeline.record(befreak_state);
+    }
+}
+
+fn assert_at_step(befreak_state: &BefreakState, start: &BefreakState, step: u64) {
+    let mut expected = start.clone();
+    expected.run_for(step);
+    assert_eq!(befreak_state.step, step);
+    assert_eq!(befreak_state.stack, expected.stack, "step {step}");
+    assert_eq!(befreak_state.control_stack, expected.control_stack);
+    assert_eq!(befreak_state.output_stack, expected.output_stack);
+    assert_eq!(befreak_state.location, expected.location, "step {step}");
+    assert_eq!(befreak_state.direction, expected.direction, "step {step}");
+    assert_eq!(befreak_state.inverse_mode, expected.inverse_mode);
+    assert_eq!(befreak_state.string_mode, expected.string_mode);
+    assert!(!befreak_state.direction_reversed);
+}
+
+#[test]
+fn seeking_matches_running_from_the_start() {
+    let start = primes();
+    let mut befreak_state = start.clone();
+    let mut timeline = Timeline::new(100, 1000);
+    run_recording(&mut befreak_state, &mut timeline, 5000);
+    assert_eq!(timeline.furthest_step(), 5000);
+
+    for step in [
+        0, 1, 99, 100, 101, 2500, 4999, 5000, 3333, 150, 149, 4321, 4320, 6000,
+    ] {
+        assert_eq!(timeline.seek(&mut befreak_state, step), step);
+        assert_at_step(&befreak_state, &start, step);
+    }
+
+    // and carries on as normal afterwards
+    timeline.seek(&mut befreak_state, 1234);
+    befreak_state.run_for(100);
+    assert_at_step(&befreak_state, &start, 1334);
+}
+
+#[test]
+fn seeking_from_a_reversed_state() {
+    let start = primes();
+    let mut befreak_state = start.clone();
+    let mut timeline = Timeline::new(100, 1000);
+    run_recording(&mut befreak_state, &mut timeline, 3000);
+    befreak_state.checked_reverse_direction();
+    befreak_state.run_for(500);
+
+    assert_eq!(timeline.seek(&mut befreak_state, 2750), 2750);
+    assert_at_step(&befreak_state, &start, 2750);
+}
+
+#[test]
+fn seeking_past_the_end_stops_there() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string("@(72w").unwrap();
+    let mut timeline = Timeline::default();
+    assert_eq!(timeline.seek(&mut befreak_state, 100), 4);
+    assert!(matches!(befreak_state.state, ExecutionState::Done));
+    assert_eq!(befreak_state.output(), "H");
+
+    assert_eq!(timeline.seek(&mut befreak_state, 2), 2);
+    assert_eq!(befreak_state.stack, [72]);
+}
+
+#[test]
+fn checkpoints_are_thinned_out() {
+    let start = primes();
+    let mut befreak_state = start.clone();
+    let mut timeline = Timeline::new(10, 16);
+    run_recording(&mut befreak_state, &mut timeline, 20_000);
+
+    let checkpoints: Vec<_> = timeline.checkpoints().collect();
+    assert!(checkpoints.len() <= 16, "{checkpoints:?}");
+    // still spread over the whole run
+    assert!(checkpoints.last().unwrap() > &15_000, "{checkpoints:?}");
+
+    assert_eq!(timeline.seek(&mut befreak_state, 12_345), 12_345);
+    assert_at_step(&befreak_state, &start, 12_345);
+}
+
+#[test]
+fn keeps_breakpoints() {
+    let start = primes();
+    let mut befreak_state = start.clone();
+    let mut timeline = Timeline::new(100, 1000);
+    run_recording(&mut befreak_state, &mut timeline, 1000);
+
+    befreak_state.toggle_breakpoint((0, 0), None);
+    timeline.seek(&mut befreak_state, 500);
+    assert_eq!(befreak_state.breakpoints.len(), 1);
+    assert_at_step(&befreak_state, &start, 500);
+}
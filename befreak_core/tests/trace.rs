@@ -0,0 +1,164 @@
+//! Traces record what every step did, and replaying them catches any step that changed.
+
+#![cfg(feature = "trace")]
+
+use befreak_core::{
+    replay, BefreakError, BefreakState, Direction, ReplayError, StackDelta, StopReason,
+    TraceRecord, Tracer, PRESETS,
+};
+
+fn record(befreak_state: &mut BefreakState, max_steps: u64) -> (StopReason, String) {
+    let mut tracer = Tracer::new(vec![]);
+    let reason = tracer.run_for(befreak_state, max_steps).unwrap();
+    (reason, String::from_utf8(tracer.into_inner()).unwrap())
+}
+
+fn records(trace: &str) -> Vec<TraceRecord<i64>> {
+    trace
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn delta(popped: &[i64], pushed: &[i64]) -> StackDelta<i64> {
+    StackDelta {
+        popped: popped.to_vec(),
+        pushed: pushed.to_vec(),
+    }
+}
+
+#[test]
+fn records_every_step() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string("@(72w").unwrap();
+    let (reason, trace) = record(&mut befreak_state, 1000);
+    assert_eq!(reason, StopReason::Halted);
+
+    let records = records(&trace);
+    assert_eq!(records.len(), 4);
+    let steps: Vec<_> = records.iter().map(|record| record.step).collect();
+    assert_eq!(steps, [1, 2, 3, 4]);
+
+    let literal = &records[1];
+    assert_eq!(literal.location, (2, 0));
+    assert_eq!(literal.instruction, '7');
+    assert_eq!(literal.effective_instruction, '7');
+    assert_eq!(literal.direction, Direction::East);
+    assert_eq!(literal.stack, delta(&[0], &[72]));
+
+    let write = &records[2];
+    assert_eq!(write.instruction, 'w');
+    assert_eq!(write.stack, delta(&[72], &[]));
+    assert_eq!(write.output_stack, delta(&[], &[72]));
+    assert_eq!(write.error, None);
+}
+
+#[test]
+fn records_reading_input() {
+    let mut start: BefreakState = BefreakState::new_from_string("@rw").unwrap();
+    start.set_input("hi".to_string());
+    let mut befreak_state = start.clone();
+    let (_, trace) = record(&mut befreak_state, 2);
+    let read = &records(&trace)[0];
+    assert_eq!(read.input_stack, delta(&['h' as i64], &[]));
+    assert_eq!(read.stack, delta(&[], &['h' as i64]));
+
+    let changed = trace.replacen(
+        "\"input_stack\":{\"popped\":[104]}",
+        "\"input_stack\":{}",
+        1,
+    );
+    assert_ne!(changed, trace);
+    match replay(&start, changed.as_bytes()) {
+        Err(ReplayError::Mismatch { line, .. }) => assert_eq!(line, 1),
+        result => panic!("expected a mismatch, got {result:?}"),
+    }
+
+    // undoing the read puts it back
+    befreak_state.checked_reverse_direction();
+    let (_, trace) = record(&mut befreak_state, 1);
+    assert_eq!(records(&trace)[0].input_stack, delta(&[], &['h' as i64]));
+}
+
+#[test]
+fn records_inverse_mapping_when_reversing() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string("@(72w").unwrap();
+    befreak_state.run_for(1000);
+    befreak_state.checked_reverse_direction();
+    let (reason, trace) = record(&mut befreak_state, 1000);
+    assert_eq!(reason, StopReason::ReturnedToStart);
+
+    let records = records(&trace);
+    let push_zero = records
+        .iter()
+        .find(|record| record.instruction == '(')
+        .unwrap();
+    assert_eq!(push_zero.effective_instruction, ')');
+    assert_eq!(push_zero.direction, Direction::West);
+    assert!(push_zero.direction_reversed);
+    assert!(push_zero.inverse_mode);
+    assert_eq!(push_zero.stack, delta(&[0], &[]));
+    assert_eq!(records.last().unwrap().step, 0);
+}
+
+#[test]
+fn records_errors() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string("@)").unwrap();
+    let (reason, trace) = record(&mut befreak_state, 1000);
+    assert!(matches!(reason, StopReason::Error(..)));
+    let records = records(&trace);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].error, Some(BefreakError::EmptyMainStack));
+    assert_eq!(records[0].stack, delta(&[], &[]));
+}
+
+#[test]
+fn presets_replay_both_ways() {
+    for preset in PRESETS {
+        let start: BefreakState = BefreakState::new_from_string(preset.code).unwrap();
+        let mut befreak_state = start.clone();
+        let (_, trace) = record(&mut befreak_state, 20_000);
+        let steps = replay(&start, trace.as_bytes());
+        assert!(steps.is_ok(), "{}: {steps:?}", preset.name);
+
+        // reversing isn't a step, so going back is replayed from after reversing
+        befreak_state.checked_reverse_direction();
+        let reversed = befreak_state.clone();
+        let (_, trace) = record(&mut befreak_state, 20_000);
+        let steps = replay(&reversed, trace.as_bytes());
+        assert!(steps.is_ok(), "{} reversed: {steps:?}", preset.name);
+    }
+}
+
+#[test]
+fn replay_finds_the_first_difference() {
+    let start: BefreakState = BefreakState::new_from_string("@(72w(105w").unwrap();
+    let (_, trace) = record(&mut start.clone(), 1000);
+
+    let changed = trace.replacen("\"pushed\":[72]", "\"pushed\":[73]", 1);
+    match replay(&start, changed.as_bytes()) {
+        Err(ReplayError::Mismatch { line, .. }) => assert_eq!(line, 2),
+        result => panic!("expected a mismatch, got {result:?}"),
+    }
+
+    let other: BefreakState = BefreakState::new_from_string("@(72w(104w").unwrap();
+    match replay(&other, trace.as_bytes()) {
+        Err(ReplayError::Mismatch { line, .. }) => assert_eq!(line, 5),
+        result => panic!("expected a mismatch, got {result:?}"),
+    }
+
+    assert!(matches!(
+        replay(&start, "not json\n".as_bytes()),
+        Err(ReplayError::InvalidRecord { line: 1, .. })
+    ));
+}
+
+#[test]
+fn replay_stops_at_errors() {
+    let start: BefreakState = BefreakState::new_from_string("@)").unwrap();
+    let (_, trace) = record(&mut start.clone(), 1000);
+    let doubled = format!("{trace}{trace}");
+    assert!(matches!(
+        replay(&start, doubled.as_bytes()),
+        Err(ReplayError::Stopped { line: 2 })
+    ));
+}
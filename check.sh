@@ -4,8 +4,10 @@ set -eux
 
 cargo check --quiet --workspace --all-targets
 cargo check --quiet --workspace --all-features --lib --target wasm32-unknown-unknown
+cargo check --quiet --package befreak_core --no-default-features
+cargo check --quiet --no-default-features --features cli
 cargo fmt --all -- --check
 cargo clippy --quiet --workspace --all-targets --all-features --  -D warnings -W clippy::all
 cargo test --quiet --workspace --all-targets --all-features
-cargo test --quiet --workspace --doc
+cargo test --quiet --workspace --doc --all-features
 trunk build
@@ -0,0 +1,149 @@
+//! Runs random programs with random steps, reverses and inputs, checking nothing panics,
+//! that going backwards over every step or run undoes it, and that each program run from the
+//! start can be undone back to it.
+//!
+//! The input is the program, optionally followed by a nul byte and what to do with it,
+//! so any program (like the ones in `seeds/`) can be used as a seed as is.
+
+#![no_main]
+
+use arbitrary::{Arbitrary, Unstructured};
+use befreak_core::{check_reversibility, BefreakState, ExecutionState, OverflowMode, StopReason};
+use libfuzzer_sys::fuzz_target;
+
+/// Going backwards hashes the stacks on every step, so this stays low to keep runs fast
+const CHECK_STEPS: u64 = 1000;
+/// Stops a long list of actions from being slow
+const MAX_ACTIONS: usize = 64;
+
+#[derive(Arbitrary, Debug)]
+enum Action {
+    Step,
+    Run(u8),
+    Reverse,
+    Reset,
+    SetInput(String),
+    SetOverflowMode(bool),
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (code, actions) = match data.iter().position(|&byte| byte == 0) {
+        Some(end) => (&data[..end], &data[end + 1..]),
+        None => (data, &[][..]),
+    };
+    let Ok(code) = std::str::from_utf8(code) else {
+        return;
+    };
+    let Ok(mut befreak_state) = BefreakState::<i64>::new_from_string(code) else {
+        return;
+    };
+
+    let mut unstructured = Unstructured::new(actions);
+    let actions = unstructured
+        .arbitrary_iter::<Action>()
+        .into_iter()
+        .flatten()
+        .map_while(Result::ok)
+        .take(MAX_ACTIONS);
+    // reversing out of an error leaves the program on an instruction that never ran,
+    // so turning around there again runs it rather than redoing it
+    let mut on_failed_instruction = false;
+    for action in actions {
+        let before = befreak_state.clone();
+        let ran = matches!(action, Action::Step | Action::Run(..));
+        match action {
+            Action::Step => befreak_state.checked_step(),
+            Action::Run(steps) => {
+                befreak_state.run_for(u64::from(steps));
+            }
+            Action::Reverse => {
+                on_failed_instruction = matches!(before.state, ExecutionState::Error(..));
+                reverse(&mut befreak_state);
+            }
+            Action::Reset => {
+                on_failed_instruction = false;
+                befreak_state.reset();
+            }
+            Action::SetInput(input) => befreak_state.set_input(input),
+            Action::SetOverflowMode(checked) => {
+                befreak_state.overflow_mode = if checked {
+                    OverflowMode::Checked
+                } else {
+                    OverflowMode::Wrapping
+                };
+            }
+        }
+
+        if ran {
+            if !on_failed_instruction {
+                check_undo(&before, &befreak_state);
+            }
+            if befreak_state.step != before.step {
+                on_failed_instruction = false;
+            }
+        }
+    }
+
+    if let Err(err) = check_reversibility(&befreak_state, CHECK_STEPS) {
+        panic!("{err}");
+    }
+});
+
+/// Whether `befreak_state` is part way through running, so can go either way without restarting
+fn mid_run(befreak_state: &BefreakState) -> bool {
+    matches!(
+        befreak_state.state,
+        ExecutionState::Running | ExecutionState::Paused(..)
+    )
+}
+
+/// Checks that going back over the steps taken from `before` to `after` ends up exactly at
+/// `before` again, the same way seeking backwards on the timeline does
+///
+/// Starting, halting, erroring and getting back to the start aren't steps that can be gone
+/// back over like this, so runs that did any of those are left to [`check_reversibility`].
+fn check_undo(before: &BefreakState, after: &BefreakState) {
+    if !mid_run(before) || !mid_run(after) {
+        return;
+    }
+    let steps = before.step.abs_diff(after.step);
+    if steps == 0 {
+        return;
+    }
+
+    // turning around undoes the last step, and turning back redoes the last one undone
+    let mut undone = after.clone();
+    undone.checked_reverse_direction();
+    let reason = undone.run_for(steps);
+    assert_eq!(reason, StopReason::StepBudget, "going back {steps} steps");
+    undone.checked_reverse_direction();
+    assert_same(&undone, before);
+}
+
+fn assert_same(actual: &BefreakState, expected: &BefreakState) {
+    assert_eq!(actual.stack, expected.stack);
+    assert_eq!(actual.control_stack, expected.control_stack);
+    assert_eq!(actual.output_stack, expected.output_stack);
+    assert_eq!(actual.input_stack, expected.input_stack);
+    assert_eq!(actual.location, expected.location);
+    assert_eq!(actual.direction, expected.direction);
+    assert_eq!(actual.direction_reversed, expected.direction_reversed);
+    assert_eq!(actual.inverse_mode, expected.inverse_mode);
+    assert_eq!(actual.string_mode, expected.string_mode);
+    assert_eq!(actual.step, expected.step);
+}
+
+/// Reverses, checking that reversing straight back would get back to the same state
+fn reverse(befreak_state: &mut BefreakState) {
+    let before = befreak_state.clone();
+    befreak_state.checked_reverse_direction();
+
+    // reversing mid-run undoes the current instruction, and reversing again redoes it
+    if matches!(before.state, ExecutionState::Running)
+        && matches!(befreak_state.state, ExecutionState::Running)
+    {
+        let mut after = befreak_state.clone();
+        after.checked_reverse_direction();
+        assert_same(&after, &before);
+    }
+}
@@ -1,11 +1,14 @@
-use phf::phf_map;
-
 use instant::Instant;
 use std::future::Future;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::time::Duration;
 
-use crate::befreak::{BefreakState, Direction, ExecutionState};
+use befreak_core::{
+    BefreakState, Condition, ConditionError, CycleDetector, Direction, ExecutionState, LoadError,
+    OverflowMode, SnapshotError, Watchpoint, PRESETS,
+};
+
+use crate::worker::{Command, WorkerHandle};
 
 // for file read
 // use std::fs::File;
@@ -15,159 +18,166 @@ use crate::befreak::{BefreakState, Direction, ExecutionState};
 // TODO:
 // changing grid size
 // make pasting with newlines functional maybe
-// make a CLI interface
 // fix adding/removing start points
 // figure out a better way to format the stack ui so they don't overflow
 
-static PRESETS: phf::Map<&'static str, &'static str> = phf_map! {
-"hello world 1" =>r#"
-/"Hello world!"01\
-\(13v     `wsv)@(/
-    \(=13=13)/    "#,
-
-"hello world 2" =>r#"
-/"Hello world!"01\
-\(13vws`v     )@(/
-    (   )         
-    =   3         
-        1         
-    \13=/         "#,
-
-"hello world 3" => r#"
-/"Hello world!"\
-\(13:vwd` v@(10/
-     \=(=)/     "#,
-
-"primes 1" => r#"
-    /1)@(1\         
-    >)1=1(<         
-    \'(v?)/         
-       >'%s(\       
-     ^ >*s)=/       
-     >=<            
-     (              
-/s'0v^?w23(v`s]:(48\
-[   (      )       +
-)   =      =       4
-0   c      c       8
-1   =      =       )
-%   )      (       w
-\01(^      ^)01*01(/"#,
-
-"primes 2" => r#"
-    /2)@(2\         
-    >)2=2(<         
-    \'(v?)/         
-       s            
-       (            
-       1            
-       >(1=1\       
-       )            
-       1    o       
-       {    *       
-       1    b       
-       (    l       
-       >)u%d/       
-       c            
-       >b'%s(= \    
-     ^ >dc=c*s)/    
-     >=<            
-     d              
-     (              
-/s'0v^?w23(v`s]:(48\
-[   (      )       +
-)   =      =       4
-0   c      c       8
-1   =      =       )
-%   )      (       w
-\01(^      ^)01*01(/"#,
-
-"error test" => r#"
-    /2)@(2\         
-    >)2=2(<         
-    \'(v?)/         
-       s            
-       (            
-       1            
-       >(1=1\       
-       )            
-       1    o       
-       {    *       
-       1    b       
-       (    l       
-       >)u%b/       
-       c            
-       >b'%s(= \    
-     ^ >dc=c*s)/    
-     >=<            
-     d              
-     (              
-/s'0v^?w23(v`s]:(48\
-[   (      )       +
-)   =      =       4
-0   c      c       8
-1   =      =       )
-%   )      (       w
-\01(^      ^)01*01(/"#,
-};
-
 pub struct AppState {
+    /// The latest snapshot from the worker, which owns the real state
     befreak_state: BefreakState,
+    worker: WorkerHandle,
     speed: f32,
     cursor_position: (usize, usize),
     paused: bool,
-    time_since_step: Instant,
     time_since_cursor: Instant,
     show_cursor: bool,
     extra: bool,
     text_channel: (Sender<String>, Receiver<String>),
+    snapshot_channel: (Sender<Vec<u8>>, Receiver<Vec<u8>>),
     hyperspeed: bool,
+    steps_per_second: f64,
+    furthest_step: u64,
+    input: String,
+    overflow_mode: OverflowMode,
+    detect_cycles: bool,
+    load_error: Option<LoadError>,
+    snapshot_error: Option<SnapshotError>,
+    breakpoint_condition: String,
+    condition_error: Option<ConditionError>,
+    watchpoint: String,
+    watchpoint_error: Option<ConditionError>,
 }
 
 impl AppState {
     /// Called once before the first frame.
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // This is also where you can customize the look and feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
+        let befreak_state = BefreakState::new_empty();
+        let speed = 5.0;
+        let worker = WorkerHandle::new(cc.egui_ctx.clone(), befreak_state.clone());
+        worker.send(Command::SetTimePerStep(time_per_step(speed)));
         Self {
-            befreak_state: BefreakState::new_empty(),
+            worker,
+            befreak_state,
             text_channel: channel(),
+            snapshot_channel: channel(),
             cursor_position: (0, 0),
-            time_since_step: Instant::now(),
             time_since_cursor: Instant::now(),
             paused: true,
             show_cursor: true,
             extra: false,
-            speed: 5.0,
+            speed,
             hyperspeed: false,
+            steps_per_second: 0.0,
+            furthest_step: 0,
+            input: String::new(),
+            overflow_mode: OverflowMode::default(),
+            detect_cycles: false,
+            load_error: None,
+            snapshot_error: None,
+            breakpoint_condition: String::new(),
+            condition_error: None,
+            watchpoint: String::new(),
+            watchpoint_error: None,
         }
     }
 
     fn reset(&mut self) {
-        self.befreak_state.reset();
+        self.worker.send(Command::Reset);
+        self.paused = true;
     }
 
     fn step(&mut self) {
-        self.befreak_state.checked_step();
+        self.worker.send(Command::Step);
+        self.paused = true;
+    }
 
-        if !matches!(self.befreak_state.state, ExecutionState::Running) {
-            self.paused = true;
-        }
+    fn seek(&mut self, step: u64) {
+        self.worker.send(Command::Seek(step));
+        self.paused = true;
     }
 
-    fn load(&mut self, data: &str) {
-        self.befreak_state = BefreakState::new_from_string(data);
+    fn set_paused(&mut self, paused: bool) {
+        self.worker
+            .send(if paused { Command::Pause } else { Command::Run });
+        self.paused = paused;
+    }
+
+    /// Replaces the program, keeping the settings that aren't tied to the code
+    fn replace(&mut self, mut befreak_state: BefreakState) {
+        // watchpoints aren't tied to the code, unlike breakpoints
+        befreak_state.watchpoints = self.befreak_state.watchpoints.clone();
+        befreak_state.set_input(self.input.clone());
+        befreak_state.overflow_mode = self.overflow_mode;
+        befreak_state.cycle_detector = self.detect_cycles.then(CycleDetector::default);
+        self.worker.send(Command::Load(Box::new(befreak_state)));
         self.paused = true;
     }
 
+    fn load(&mut self, data: &str) {
+        match BefreakState::new_from_string(data) {
+            Ok(befreak_state) => {
+                self.replace(befreak_state);
+                self.load_error = None;
+                self.snapshot_error = None;
+            }
+            Err(err) => self.load_error = Some(err),
+        }
+    }
+
+    /// Loads the whole state from a snapshot, including the settings it was saved with
+    fn load_snapshot(&mut self, bytes: &[u8]) {
+        match BefreakState::from_snapshot(bytes) {
+            Ok(befreak_state) => {
+                self.input = String::from_utf8_lossy(&befreak_state.input).into_owned();
+                self.overflow_mode = befreak_state.overflow_mode;
+                self.detect_cycles = befreak_state.cycle_detector.is_some();
+                self.worker.send(Command::Load(Box::new(befreak_state)));
+                self.paused = true;
+                self.load_error = None;
+                self.snapshot_error = None;
+            }
+            Err(err) => self.snapshot_error = Some(err),
+        }
+    }
+
     fn new_file(&mut self) {
-        self.befreak_state = BefreakState::new_empty();
-        self.paused = true;
+        self.replace(BefreakState::new_empty());
+        self.load_error = None;
+        self.snapshot_error = None;
+    }
+
+    fn toggle_breakpoint(&mut self) {
+        let condition = if self.breakpoint_condition.trim().is_empty() {
+            None
+        } else {
+            match self.breakpoint_condition.parse::<Condition>() {
+                Ok(condition) => Some(condition),
+                Err(err) => {
+                    self.condition_error = Some(err);
+                    return;
+                }
+            }
+        };
+        self.condition_error = None;
+        self.worker
+            .send(Command::ToggleBreakpoint(self.cursor_position, condition));
+    }
+
+    fn add_watchpoint(&mut self) {
+        match self.watchpoint.parse::<Watchpoint>() {
+            Ok(watchpoint) => {
+                self.worker.send(Command::AddWatchpoint(watchpoint));
+                self.watchpoint.clear();
+                self.watchpoint_error = None;
+            }
+            Err(err) => self.watchpoint_error = Some(err),
+        }
     }
 
     fn reverse_direction(&mut self) {
-        self.befreak_state.checked_reverse_direction();
+        self.worker.send(Command::Reverse);
     }
 }
 
@@ -180,6 +190,15 @@ impl eframe::App for AppState {
         if let Ok(text) = self.text_channel.1.try_recv() {
             self.load(&text);
         }
+        if let Ok(bytes) = self.snapshot_channel.1.try_recv() {
+            self.load_snapshot(&bytes);
+        }
+        if let Some(snapshot) = self.worker.poll() {
+            self.befreak_state = snapshot.befreak_state;
+            self.paused = !snapshot.running;
+            self.steps_per_second = snapshot.steps_per_second;
+            self.furthest_step = snapshot.furthest_step;
+        }
 
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
@@ -221,19 +240,56 @@ impl eframe::App for AppState {
                             }
                         });
                     }
+
+                    ui.separator();
+
+                    if ui.button("📂 Open snapshot").clicked() {
+                        let sender = self.snapshot_channel.0.clone();
+                        let task = rfd::AsyncFileDialog::new().pick_file();
+                        let ctx = ui.ctx().clone();
+                        execute(async move {
+                            let file = task.await;
+                            if let Some(file) = file {
+                                let _ = sender.send(file.read().await);
+                                ctx.request_repaint();
+                            }
+                        });
+                    }
+
+                    // the whole state, to share exactly where a program got to
+                    if ui.button("💾 Save snapshot").clicked() {
+                        match self.befreak_state.to_json_snapshot() {
+                            Ok(contents) => {
+                                let task = rfd::AsyncFileDialog::new()
+                                    .set_file_name("snapshot.json")
+                                    .save_file();
+                                execute(async move {
+                                    let file = task.await;
+                                    if let Some(file) = file {
+                                        _ = file.write(contents.as_bytes()).await;
+                                    }
+                                });
+                            }
+                            Err(err) => self.snapshot_error = Some(err),
+                        }
+                    }
                 });
 
                 ui.menu_button("Settings", |ui| {
                     ui.checkbox(&mut self.extra, "extra info");
+                    if ui
+                        .checkbox(&mut self.detect_cycles, "detect infinite loops")
+                        .changed()
+                    {
+                        self.worker
+                            .send(Command::SetCycleDetection(self.detect_cycles));
+                    }
                 });
 
                 ui.menu_button("Presets", |ui| {
-                    for key in PRESETS.keys() {
-                        if ui.button(*key).clicked() {
-                            match PRESETS.get(key) {
-                                None => unreachable!(),
-                                Some(data) => self.load(data),
-                            }
+                    for preset in PRESETS {
+                        if ui.button(preset.name).clicked() {
+                            self.load(preset.code);
                         }
                     }
                 });
@@ -246,7 +302,9 @@ impl eframe::App for AppState {
 
         egui::CentralPanel::default().show(ctx, |ui| {
             let mut direction = None;
-            if ui.input(|e| e.key_pressed(egui::Key::ArrowDown)) {
+            // don't edit the grid while typing into a text box (like the input)
+            if ui.memory(|mem| mem.focused().is_some()) {
+            } else if ui.input(|e| e.key_pressed(egui::Key::ArrowDown)) {
                 direction = Some(Direction::South);
             } else if ui.input(|e| e.key_pressed(egui::Key::ArrowUp)) {
                 direction = Some(Direction::North);
@@ -265,11 +323,8 @@ impl eframe::App for AppState {
                         match event {
                             egui::Event::Text(text) | egui::Event::Paste(text) => {
                                 for char in text.chars() {
-                                    let _ = self.befreak_state.code.set(
-                                        self.cursor_position.1,
-                                        self.cursor_position.0,
-                                        char,
-                                    );
+                                    self.worker
+                                        .send(Command::SetCell(self.cursor_position, char));
                                     if self.cursor_position
                                         == (
                                             self.befreak_state.code.row_len() - 1,
@@ -302,27 +357,18 @@ impl eframe::App for AppState {
                 self.show_cursor = true;
                 self.time_since_cursor = Instant::now();
             }
-            let time_per_step = Duration::from_millis((500.0 - 49.0 * self.speed) as u64);
-            let elapsed = self.time_since_step.elapsed();
-            if !self.paused {
-                if self.hyperspeed {
-                    self.step();
-                    ui.ctx().request_repaint();
-                } else {
-                    if elapsed >= time_per_step {
-                        self.step();
-                        self.time_since_step = Instant::now();
-                    }
-
-                    ui.ctx().request_repaint_after(time_per_step);
-                }
-            }
 
             // The central panel the region left after adding TopPanel's and SidePanel's
             ui.horizontal(|ui| {
                 ui.heading("Befreak interpreter");
-                if let ExecutionState::Error(error) = &self.befreak_state.state {
+                if let Some(error) = &self.load_error {
+                    ui.label(egui::RichText::new(error.to_string()).color(egui::Color32::RED));
+                } else if let Some(error) = &self.snapshot_error {
                     ui.label(egui::RichText::new(error.to_string()).color(egui::Color32::RED));
+                } else if let ExecutionState::Error(error) = &self.befreak_state.state {
+                    ui.label(egui::RichText::new(error.to_string()).color(egui::Color32::RED));
+                } else if let ExecutionState::Paused(reason) = &self.befreak_state.state {
+                    ui.label(reason.to_string());
                 };
             });
 
@@ -332,15 +378,12 @@ impl eframe::App for AppState {
                     |ui| {
                         if ui.button("step").clicked() {
                             self.step();
-                            if matches!(self.befreak_state.state, ExecutionState::Running) {
-                                self.paused = true;
-                            }
                         };
                         if ui
                             .button(if self.paused { "unpause" } else { "pause" })
                             .clicked()
                         {
-                            self.paused = !self.paused;
+                            self.set_paused(!self.paused);
                         };
                     },
                 );
@@ -363,27 +406,103 @@ impl eframe::App for AppState {
 
                 if ui.button("restart").clicked() {
                     self.reset();
-                    self.paused = true;
                 }
-                ui.add(egui::Slider::new(&mut self.speed, 1.0..=10.0).text("speed"));
-                if ui.button("max speed").clicked() {
-                    self.hyperspeed = !self.hyperspeed;
+                if ui
+                    .add(egui::Slider::new(&mut self.speed, 1.0..=10.0).text("speed"))
+                    .changed()
+                {
+                    self.worker
+                        .send(Command::SetTimePerStep(time_per_step(self.speed)));
+                }
+                if ui.toggle_value(&mut self.hyperspeed, "max speed").clicked() {
+                    self.worker.send(Command::SetMaxSpeed(self.hyperspeed));
+                    self.steps_per_second = 0.0;
+                }
+                if self.hyperspeed {
+                    ui.label(format!("{:.0} steps/s", self.steps_per_second));
+                }
+            });
+
+            // drag to jump to any step up to the furthest the program has got
+            ui.horizontal(|ui| {
+                let mut step = self.befreak_state.step;
+                let end = self.furthest_step.max(step);
+                ui.spacing_mut().slider_width = (ui.available_width() - 100.0).max(100.0);
+                if ui
+                    .add(egui::Slider::new(&mut step, 0..=end).text("step"))
+                    .changed()
+                {
+                    self.seek(step);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("input");
+                ui.add_enabled_ui(
+                    matches!(self.befreak_state.state, ExecutionState::NotStarted),
+                    |ui| {
+                        if ui.text_edit_singleline(&mut self.input).changed() {
+                            self.worker.send(Command::SetInput(self.input.clone()));
+                        }
+                        let mut checked = self.overflow_mode == OverflowMode::Checked;
+                        if ui.checkbox(&mut checked, "error on overflow").changed() {
+                            self.overflow_mode = if checked {
+                                OverflowMode::Checked
+                            } else {
+                                OverflowMode::Wrapping
+                            };
+                            self.worker
+                                .send(Command::SetOverflowMode(self.overflow_mode));
+                        }
+                    },
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("breakpoint condition");
+                ui.text_edit_singleline(&mut self.breakpoint_condition)
+                    .on_hover_text("like \"top == 5\", leave empty to always pause");
+                if ui.button("toggle breakpoint at cursor").clicked() {
+                    self.toggle_breakpoint();
+                }
+                if let Some(error) = &self.condition_error {
+                    ui.label(egui::RichText::new(error.to_string()).color(egui::Color32::RED));
                 }
             });
 
+            ui.horizontal(|ui| {
+                ui.label("watchpoint");
+                ui.text_edit_singleline(&mut self.watchpoint).on_hover_text(
+                    "\"top changed\", \"control changed\", \"output grows\", \
+                    or a condition like \"depth == 10\" to pause when it starts holding",
+                );
+                if ui.button("add watchpoint").clicked() {
+                    self.add_watchpoint();
+                }
+                if let Some(error) = &self.watchpoint_error {
+                    ui.label(egui::RichText::new(error.to_string()).color(egui::Color32::RED));
+                }
+            });
+            let mut removed = None;
+            for (index, watchpoint) in self.befreak_state.watchpoints.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(watchpoint.to_string());
+                    if ui.button("remove").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                self.worker.send(Command::RemoveWatchpoint(index));
+            }
+
             ui.separator();
 
             ui.horizontal(|ui| {
                 ui.columns(3, |cols| {
                     cols[0].vertical_centered_justified(|ui| {
                         ui.label("output");
-                        let output = &self
-                            .befreak_state
-                            .output_stack
-                            .iter()
-                            .map(|x| *x as u8 as char)
-                            .collect::<String>();
-                        ui.label(output);
+                        ui.label(self.befreak_state.output());
                     });
                     //TODO: these don't fit if the stack is too full
                     cols[1].vertical_centered_justified(|ui| {
@@ -434,8 +553,8 @@ impl eframe::App for AppState {
                         ui.label(format!("{:?}", self.befreak_state.string_mode));
                     });
                     ui.vertical(|ui| {
-                        ui.label("time diff");
-                        ui.label(format!("{elapsed:.3?}"));
+                        ui.label("steps per second");
+                        ui.label(format!("{:.0}", self.steps_per_second));
                     });
                     for value in &self.befreak_state.stack {
                         ui.label(String::from(*value as u8 as char));
@@ -478,7 +597,7 @@ impl eframe::App for AppState {
                 self.time_since_cursor = Instant::now();
             }
 
-            ui.ctx().request_repaint_after(time_per_step);
+            ui.ctx().request_repaint_after(cursor_flash_delay);
 
             egui::Grid::new("letter_grid")
                 .spacing([0.0, 0.0])
@@ -498,6 +617,17 @@ impl eframe::App for AppState {
                                         .background_color(position_color)
                                         .family(egui::FontFamily::Monospace),
                                 );
+                            } else if self
+                                .befreak_state
+                                .breakpoints
+                                .iter()
+                                .any(|breakpoint| breakpoint.location == (index_x, index_y))
+                            {
+                                ui.label(
+                                    egui::RichText::new(*c)
+                                        .background_color(egui::Color32::DARK_RED)
+                                        .family(egui::FontFamily::Monospace),
+                                );
                             } else {
                                 ui.label(
                                     egui::RichText::new(*c).family(egui::FontFamily::Monospace),
@@ -523,6 +653,10 @@ impl eframe::App for AppState {
     }
 }
 
+fn time_per_step(speed: f32) -> Duration {
+    Duration::from_millis((500.0 - 49.0 * speed) as u64)
+}
+
 fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
     ui.horizontal(|ui| {
         ui.spacing_mut().item_spacing.x = 0.0;
@@ -0,0 +1,326 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::process::ExitCode;
+
+use befreak_core::{
+    check_reversibility, replay, BefreakState, BigInt, Cell, CycleDetector, OverflowMode,
+    StopReason, Tracer, Watchpoint,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const USAGE: &str = "usage: befreak [options] <file>
+       befreak check-reversible [--steps <n>] [options] <file>
+       befreak resume [options] <snapshot>
+       befreak replay <trace> [options] <file>
+
+check-reversible runs the program forwards for up to n steps (default 1000000),
+then checks going backwards undoes every step and gets back to the start
+
+resume carries on from a snapshot saved with --save-snapshot (or from the GUI),
+with the input it was saved with instead of stdin
+
+replay runs the program again, checking every step matches a trace saved with --trace
+
+options:
+    --bigint                 use arbitrary precision values instead of wrapping 64 bit ones
+    --checked                stop with an error on overflow instead of wrapping
+    --detect-cycles          stop with an error if the program gets stuck in a loop
+    --watch <watchpoint>     print the stacks to stderr whenever the watchpoint goes off,
+                             like \"top changed\", \"control changed\", \"output grows\",
+                             or a condition starting to hold like \"depth == 10\"
+    --save-snapshot <file>   save the whole state as JSON when the program stops
+    --trace <file>           write a line of JSON for every step, saying what it did
+                             (not with check-reversible or replay)";
+
+const DEFAULT_CHECK_STEPS: u64 = 1_000_000;
+
+struct Options {
+    path: String,
+    /// The maximum steps to check with `check-reversible`, otherwise the program is just run
+    check_steps: Option<u64>,
+    /// Whether `path` is a snapshot to carry on from, rather than a program
+    resume: bool,
+    /// A trace to check the program against, otherwise the program is just run
+    replay: Option<String>,
+    bigint: bool,
+    checked: bool,
+    watchpoints: Vec<Watchpoint>,
+    detect_cycles: bool,
+    save_snapshot: Option<String>,
+    trace: Option<String>,
+}
+
+impl Options {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.peekable();
+        let mut check_steps = None;
+        let mut resume = false;
+        let mut replay = None;
+        if args.next_if(|arg| arg == "check-reversible").is_some() {
+            check_steps = Some(DEFAULT_CHECK_STEPS);
+        } else if args.next_if(|arg| arg == "resume").is_some() {
+            resume = true;
+        } else if args.next_if(|arg| arg == "replay").is_some() {
+            replay = Some(args.next().ok_or("replay needs a trace")?);
+        }
+
+        let mut path = None;
+        let mut bigint = false;
+        let mut checked = false;
+        let mut watchpoints = vec![];
+        let mut detect_cycles = false;
+        let mut save_snapshot = None;
+        let mut trace = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bigint" => bigint = true,
+                "--checked" => checked = true,
+                "--detect-cycles" => detect_cycles = true,
+                "--steps" if check_steps.is_some() => {
+                    let steps = args.next().ok_or("--steps needs a number")?;
+                    let steps = steps
+                        .parse()
+                        .map_err(|err| format!("invalid number of steps {steps:?}: {err}"))?;
+                    check_steps = Some(steps);
+                }
+                "--watch" => {
+                    let watchpoint = args.next().ok_or("--watch needs a watchpoint")?;
+                    let watchpoint = watchpoint
+                        .parse()
+                        .map_err(|err| format!("invalid watchpoint {watchpoint:?}: {err}"))?;
+                    watchpoints.push(watchpoint);
+                }
+                "--save-snapshot" => {
+                    let file = args.next().ok_or("--save-snapshot needs a file")?;
+                    save_snapshot = Some(file);
+                }
+                "--trace" => {
+                    let file = args.next().ok_or("--trace needs a file")?;
+                    trace = Some(file);
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+                _ if path.is_none() => path = Some(arg),
+                _ => return Err(format!("unexpected argument {arg}")),
+            }
+        }
+        let path = path.ok_or("no file given")?;
+        // neither runs the program in a way that makes a trace
+        if trace.is_some() && check_steps.is_some() {
+            return Err("--trace can't be used with check-reversible".to_string());
+        }
+        if trace.is_some() && replay.is_some() {
+            return Err("--trace can't be used with replay".to_string());
+        }
+        Ok(Self {
+            path,
+            check_steps,
+            resume,
+            replay,
+            bigint,
+            checked,
+            watchpoints,
+            detect_cycles,
+            save_snapshot,
+            trace,
+        })
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let contents = match std::fs::read(&options.path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            eprintln!("error: could not read {}: {err}", options.path);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // input has to be known up front so `r` can be undone,
+    // and waiting on a terminal would hang programs that never read
+    let mut input = vec![];
+    let mut stdin = io::stdin();
+    if !options.resume && !stdin.is_terminal() {
+        if let Err(err) = stdin.read_to_end(&mut input) {
+            eprintln!("error: could not read stdin: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if options.bigint {
+        run::<BigInt>(&options, &contents, input)
+    } else {
+        run::<i64>(&options, &contents, input)
+    }
+}
+
+fn load<T>(options: &Options, contents: &[u8], input: Vec<u8>) -> Result<BefreakState<T>, String>
+where
+    T: Cell + Serialize + DeserializeOwned,
+{
+    if options.resume {
+        return BefreakState::from_snapshot(contents)
+            .map_err(|err| format!("could not resume from {}: {err}", options.path));
+    }
+
+    let code = String::from_utf8_lossy(contents);
+    let mut befreak_state = BefreakState::new_from_string(&code)
+        .map_err(|err| format!("could not load {}: {err}", options.path))?;
+    befreak_state.set_input(input);
+    Ok(befreak_state)
+}
+
+fn save_snapshot<T>(options: &Options, befreak_state: &BefreakState<T>)
+where
+    T: Cell + Serialize + DeserializeOwned,
+{
+    let Some(path) = &options.save_snapshot else {
+        return;
+    };
+    let result = befreak_state
+        .to_json_snapshot()
+        .map_err(|err| err.to_string())
+        .and_then(|json| std::fs::write(path, json).map_err(|err| err.to_string()));
+    match result {
+        Ok(()) => eprintln!("saved a snapshot of step {} to {path}", befreak_state.step),
+        Err(err) => eprintln!("error: could not save a snapshot to {path}: {err}"),
+    }
+}
+
+fn run<T>(options: &Options, contents: &[u8], input: Vec<u8>) -> ExitCode
+where
+    T: Cell + Serialize + DeserializeOwned,
+{
+    let mut befreak_state = match load::<T>(options, contents, input) {
+        Ok(befreak_state) => befreak_state,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // a resumed run keeps what it was saved with, unless told otherwise
+    if options.checked {
+        befreak_state.overflow_mode = OverflowMode::Checked;
+    }
+    befreak_state
+        .watchpoints
+        .extend(options.watchpoints.iter().cloned());
+    if options.detect_cycles && befreak_state.cycle_detector.is_none() {
+        befreak_state.cycle_detector = Some(CycleDetector::default());
+    }
+
+    if let Some(max_steps) = options.check_steps {
+        return match check_reversibility(&befreak_state, max_steps) {
+            Ok(steps) => {
+                println!("reversible: ran {steps} steps forwards and back to the start");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("error: {err}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if let Some(path) = &options.replay {
+        let result = File::open(path)
+            .map_err(|err| format!("could not read {path}: {err}"))
+            .and_then(|file| {
+                replay(&befreak_state, BufReader::new(file)).map_err(|err| err.to_string())
+            });
+        return match result {
+            Ok(steps) => {
+                println!("matches: replayed {steps} steps from {path}");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("error: {err}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let mut tracer = match &options.trace {
+        Some(path) => match File::create(path) {
+            Ok(file) => Some(Tracer::new(BufWriter::new(file))),
+            Err(err) => {
+                eprintln!("error: could not create {path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    let mut stdout = io::stdout().lock();
+    // a resumed run has already written its output
+    let mut written = befreak_state.output_stack.len();
+    loop {
+        let reason = match &mut tracer {
+            Some(tracer) => match tracer.run_until_output(&mut befreak_state, u64::MAX) {
+                Ok(reason) => reason,
+                Err(err) => {
+                    let _ = stdout.flush();
+                    eprintln!("error: {err}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            None => befreak_state.run_until_output(u64::MAX),
+        };
+
+        // going backwards takes values off the output stack, but they've already been printed
+        written = written.min(befreak_state.output_stack.len());
+        for value in &befreak_state.output_stack[written..] {
+            let _ = stdout.write_all(&[value.to_byte()]);
+        }
+        written = befreak_state.output_stack.len();
+
+        match reason {
+            // carrying on from the start would run the whole program forwards again
+            StopReason::Halted | StopReason::ReturnedToStart => break,
+            StopReason::Error(err) => {
+                let _ = stdout.flush();
+                eprintln!("error: {err}");
+                save_snapshot(options, &befreak_state);
+                return ExitCode::FAILURE;
+            }
+            StopReason::Cycle { period, first_seen } => {
+                let _ = stdout.flush();
+                eprintln!(
+                    "error: stuck in a loop repeating every {period} steps, \
+                    first seen on step {first_seen}"
+                );
+                save_snapshot(options, &befreak_state);
+                return ExitCode::FAILURE;
+            }
+            // there's no one to resume it, so just report it and keep going
+            StopReason::Paused(reason) => {
+                let _ = stdout.flush();
+                eprintln!(
+                    "{reason} on step {}, stack: {:?}, control stack: {:?}",
+                    befreak_state.step, befreak_state.stack, befreak_state.control_stack
+                );
+            }
+            StopReason::Output | StopReason::StepBudget => (),
+        }
+    }
+
+    let _ = stdout.flush();
+    save_snapshot(options, &befreak_state);
+    if let Some(tracer) = tracer {
+        if let Err(err) = tracer.into_inner().flush() {
+            eprintln!("error: could not write the trace: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -1,6 +1,15 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+//! The egui app for the befreak interpreter.
+//!
+//! The interpreter itself is the `befreak_core` crate, which doesn't depend on the GUI.
+//! It is re-exported here as [`befreak`].
+
+#[cfg(feature = "gui")]
 mod app;
+#[cfg(feature = "gui")]
+mod worker;
+#[cfg(feature = "gui")]
 pub use app::AppState;
 
-mod befreak;
+pub use befreak_core as befreak;
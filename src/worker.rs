@@ -0,0 +1,297 @@
+//! Runs the interpreter away from the UI, so slow programs never stop it from drawing.
+//!
+//! On native the worker is a thread, on the web there are no threads so it runs
+//! a frame's worth of steps each time the UI polls it instead.
+
+use instant::Instant;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use befreak_core::{
+    BefreakState, Condition, CycleDetector, ExecutionState, OverflowMode, StopReason, Timeline,
+    Watchpoint,
+};
+
+/// How long max speed runs before publishing a snapshot, about a frame
+const MAX_SPEED_FRAME_TIME: Duration = Duration::from_millis(12);
+/// Steps run between checking the time at max speed
+const MAX_SPEED_CHUNK: u64 = 1000;
+
+pub enum Command {
+    /// Runs a single step, pausing if it was running
+    Step,
+    Run,
+    Pause,
+    Reverse,
+    Reset,
+    /// Jumps to a step, pausing if it was running
+    Seek(u64),
+    Load(Box<BefreakState>),
+    SetCell((usize, usize), char),
+    SetInput(String),
+    SetOverflowMode(OverflowMode),
+    ToggleBreakpoint((usize, usize), Option<Condition>),
+    AddWatchpoint(Watchpoint),
+    RemoveWatchpoint(usize),
+    SetTimePerStep(Duration),
+    SetMaxSpeed(bool),
+    SetCycleDetection(bool),
+}
+
+/// A copy of the worker's state for the UI to draw
+pub struct Snapshot {
+    pub befreak_state: BefreakState,
+    pub running: bool,
+    pub steps_per_second: f64,
+    /// The furthest step the program has got to, for the end of the timeline
+    pub furthest_step: u64,
+}
+
+struct Worker {
+    befreak_state: BefreakState,
+    timeline: Timeline,
+    commands: Receiver<Command>,
+    snapshot: Arc<Mutex<Option<Snapshot>>>,
+    ctx: egui::Context,
+    running: bool,
+    max_speed: bool,
+    time_per_step: Duration,
+    time_since_step: Instant,
+    steps_per_second: f64,
+    speed_measure_start: Instant,
+    speed_measure_steps: u64,
+    changed: bool,
+}
+
+impl Worker {
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Step => {
+                self.running = false;
+                self.step();
+            }
+            Command::Run => {
+                self.running = true;
+                self.speed_measure_start = Instant::now();
+                self.speed_measure_steps = 0;
+            }
+            Command::Pause => self.running = false,
+            Command::Reverse => self.befreak_state.checked_reverse_direction(),
+            Command::Reset => {
+                self.befreak_state.reset();
+                self.running = false;
+            }
+            Command::Seek(step) => {
+                self.timeline.seek(&mut self.befreak_state, step);
+                self.running = false;
+            }
+            Command::Load(befreak_state) => {
+                self.befreak_state = *befreak_state;
+                self.timeline.clear();
+                self.timeline.record(&self.befreak_state);
+                self.running = false;
+            }
+            // anything that changes what the program does makes the checkpoints wrong
+            Command::SetCell((x, y), char) => {
+                let _ = self.befreak_state.code.set(y, x, char);
+                self.timeline.clear();
+            }
+            Command::SetInput(input) => {
+                self.befreak_state.set_input(input);
+                self.timeline.clear();
+            }
+            Command::SetOverflowMode(overflow_mode) => {
+                self.befreak_state.overflow_mode = overflow_mode;
+                self.timeline.clear();
+            }
+            Command::ToggleBreakpoint(location, condition) => {
+                self.befreak_state.toggle_breakpoint(location, condition);
+            }
+            Command::AddWatchpoint(watchpoint) => self.befreak_state.watchpoints.push(watchpoint),
+            Command::RemoveWatchpoint(index) => {
+                if index < self.befreak_state.watchpoints.len() {
+                    self.befreak_state.watchpoints.remove(index);
+                }
+            }
+            Command::SetCycleDetection(detect_cycles) => {
+                self.befreak_state.cycle_detector = detect_cycles.then(CycleDetector::default);
+            }
+            Command::SetTimePerStep(time_per_step) => self.time_per_step = time_per_step,
+            Command::SetMaxSpeed(max_speed) => {
+                self.max_speed = max_speed;
+                self.speed_measure_start = Instant::now();
+                self.speed_measure_steps = 0;
+                self.steps_per_second = 0.0;
+            }
+        }
+        self.changed = true;
+    }
+
+    fn step(&mut self) {
+        let step = self.befreak_state.step;
+        self.befreak_state.checked_step();
+        self.timeline.record(&self.befreak_state);
+        self.speed_measure_steps += self.befreak_state.step.abs_diff(step);
+
+        if !matches!(self.befreak_state.state, ExecutionState::Running) {
+            self.running = false;
+        }
+        self.changed = true;
+    }
+
+    /// Runs as many steps as fit in a frame
+    fn run_max_speed(&mut self) {
+        let start = Instant::now();
+        while start.elapsed() < MAX_SPEED_FRAME_TIME {
+            let step = self.befreak_state.step;
+            let reason = self.befreak_state.run_for(MAX_SPEED_CHUNK);
+            self.timeline.record(&self.befreak_state);
+            self.speed_measure_steps += self.befreak_state.step.abs_diff(step);
+            if reason != StopReason::StepBudget {
+                self.running = false;
+                break;
+            }
+        }
+        self.changed = true;
+    }
+
+    /// Handles any waiting commands and runs whatever steps are due,
+    /// returning false once the UI has gone away
+    fn tick(&mut self) -> bool {
+        loop {
+            match self.commands.try_recv() {
+                Ok(command) => self.handle(command),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+
+        if self.running {
+            if self.max_speed {
+                self.run_max_speed();
+            } else if self.time_since_step.elapsed() >= self.time_per_step {
+                self.step();
+                self.time_since_step = Instant::now();
+            }
+        }
+
+        let elapsed = self.speed_measure_start.elapsed();
+        if elapsed >= Duration::from_millis(500) {
+            self.steps_per_second = self.speed_measure_steps as f64 / elapsed.as_secs_f64();
+            self.speed_measure_start = Instant::now();
+            self.speed_measure_steps = 0;
+        }
+
+        if self.changed {
+            self.publish();
+        }
+        true
+    }
+
+    fn publish(&mut self) {
+        *self.snapshot.lock().unwrap() = Some(Snapshot {
+            befreak_state: self.befreak_state.clone(),
+            running: self.running,
+            steps_per_second: self.steps_per_second,
+            furthest_step: self.timeline.furthest_step(),
+        });
+        self.changed = false;
+        self.ctx.request_repaint();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_thread(mut self) {
+        use std::sync::mpsc::RecvTimeoutError;
+
+        while self.tick() {
+            if !self.running {
+                // nothing to do until the UI asks for something
+                match self.commands.recv() {
+                    Ok(command) => self.handle(command),
+                    Err(_) => return,
+                }
+            } else if !self.max_speed {
+                let wait = self
+                    .time_per_step
+                    .saturating_sub(self.time_since_step.elapsed());
+                match self.commands.recv_timeout(wait) {
+                    Ok(command) => self.handle(command),
+                    Err(RecvTimeoutError::Timeout) => (),
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        }
+    }
+}
+
+/// The UI's side of the worker, which stops the worker when dropped
+pub struct WorkerHandle {
+    commands: Sender<Command>,
+    snapshot: Arc<Mutex<Option<Snapshot>>>,
+    #[cfg(target_arch = "wasm32")]
+    worker: Worker,
+}
+
+impl WorkerHandle {
+    pub fn new(ctx: egui::Context, befreak_state: BefreakState) -> Self {
+        let (commands, receiver) = channel();
+        let snapshot = Arc::new(Mutex::new(None));
+        let mut worker = Worker {
+            befreak_state,
+            timeline: Timeline::default(),
+            commands: receiver,
+            snapshot: snapshot.clone(),
+            ctx,
+            running: false,
+            max_speed: false,
+            time_per_step: Duration::ZERO,
+            time_since_step: Instant::now(),
+            steps_per_second: 0.0,
+            speed_measure_start: Instant::now(),
+            speed_measure_steps: 0,
+            changed: false,
+        };
+        worker.publish();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(move || worker.run_thread());
+
+        Self {
+            commands,
+            snapshot,
+            #[cfg(target_arch = "wasm32")]
+            worker,
+        }
+    }
+
+    pub fn send(&self, command: Command) {
+        // the worker only stops once this handle is dropped
+        let _ = self.commands.send(command);
+        #[cfg(target_arch = "wasm32")]
+        self.worker.ctx.request_repaint();
+    }
+
+    /// The newest snapshot, if there has been one since the last poll
+    ///
+    /// On the web this is also what runs the worker, so it needs calling every frame.
+    pub fn poll(&mut self) -> Option<Snapshot> {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.worker.tick();
+            if self.worker.running {
+                if self.worker.max_speed {
+                    self.worker.ctx.request_repaint();
+                } else {
+                    let wait = self
+                        .worker
+                        .time_per_step
+                        .saturating_sub(self.worker.time_since_step.elapsed());
+                    self.worker.ctx.request_repaint_after(wait);
+                }
+            }
+        }
+
+        self.snapshot.lock().unwrap().take()
+    }
+}
@@ -0,0 +1,115 @@
+//! The `befreak` binary passes stdin through as bytes, resumes snapshots in either direction,
+//! and rejects options that would be ignored.
+
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use befreak_core::{BefreakState, ExecutionState, StopReason};
+
+const HI: &str = "@(72w(105w";
+
+/// A file in the temporary directory, removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        let file_name = format!("befreak-cli-{}-{name}", std::process::id());
+        Self(std::env::temp_dir().join(file_name))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Saves `befreak_state` as a snapshot, then resumes it, saving where it ended up
+fn resume(name: &str, befreak_state: &BefreakState) -> (Output, BefreakState) {
+    let snapshot = TempFile::new(&format!("{name}.json"));
+    let saved = TempFile::new(&format!("{name}-saved.json"));
+    std::fs::write(&snapshot.0, befreak_state.to_json_snapshot().unwrap()).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_befreak"))
+        .arg("resume")
+        .arg("--save-snapshot")
+        .arg(&saved.0)
+        .arg(&snapshot.0)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{name}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let saved = BefreakState::from_snapshot(&std::fs::read(&saved.0).unwrap()).unwrap();
+    (output, saved)
+}
+
+#[test]
+fn resumes_going_forwards() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string(HI).unwrap();
+    assert_eq!(befreak_state.run_until_output(100), StopReason::Output);
+
+    // the H was written before the snapshot was taken
+    let (output, saved) = resume("forwards", &befreak_state);
+    assert_eq!(output.stdout, b"i");
+    assert!(matches!(saved.state, ExecutionState::Done));
+    assert_eq!(saved.output(), "Hi");
+}
+
+#[test]
+fn resumes_going_backwards() {
+    let mut befreak_state: BefreakState = BefreakState::new_from_string(HI).unwrap();
+    assert_eq!(befreak_state.run_for(100), StopReason::Halted);
+    befreak_state.checked_reverse_direction();
+
+    // undoing the output doesn't print anything, and it stops back at the start
+    let (output, saved) = resume("backwards", &befreak_state);
+    assert_eq!(output.stdout, b"");
+    assert!(matches!(saved.state, ExecutionState::NotStarted));
+    assert_eq!(saved.step, 0);
+    assert_eq!(saved.output(), "");
+}
+
+#[test]
+fn reads_stdin_as_bytes() {
+    let program = TempFile::new("echo.txt");
+    std::fs::write(&program.0, "@rwrwrw").unwrap();
+    let input = [0xff, 0xc3, 0xa9];
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_befreak"))
+        .arg(&program.0)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.stdout, input);
+}
+
+#[test]
+fn trace_only_runs_the_program() {
+    for subcommand in [&["check-reversible"][..], &["replay", "trace.jsonl"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_befreak"))
+            .args(subcommand)
+            .args(["--trace", "trace.jsonl", "program.txt"])
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("--trace can't be used with"), "{stderr}");
+    }
+}